@@ -5,6 +5,7 @@ use odra_cli::{deploy::DeployScript, DeployedContractsContainer, OdraCli};
 use std::str::FromStr;
 
 use cusd::cusd::{CUSDInitArgs, CUSD};
+use keeper_bonds::keeper_bonds::{KeeperBonds, KeeperBondsInitArgs};
 use liquid_staking::liquid_staking::{LiquidStaking, LiquidStakingInitArgs};
 use oracle::oracle::{PriceOracle, PriceOracleInitArgs};
 use stayer::stayer::{StayerVault, StayerVaultInitArgs};
@@ -47,7 +48,7 @@ impl DeployScript for DeployStayerScript {
         // Auction contract is handled by keeper, not used in contract init
         // hash-93d923e336b20a4c4ca14d592b60e5bd3fe330775618290104f9beb326db7ae2
 
-        let validator_registry =
+        let mut validator_registry =
             ValidatorRegistry::try_deploy(env, ValidatorRegistryInitArgs { keeper_address })?;
         container.add_contract(&validator_registry)?;
 
@@ -67,7 +68,7 @@ impl DeployScript for DeployStayerScript {
         )?;
         container.add_contract(&yscspr)?;
 
-        let liquid_staking = LiquidStaking::try_deploy(
+        let mut liquid_staking = LiquidStaking::try_deploy(
             env,
             LiquidStakingInitArgs {
                 validator_registry: validator_registry.address(),
@@ -77,6 +78,19 @@ impl DeployScript for DeployStayerScript {
         )?;
         container.add_contract(&liquid_staking)?;
 
+        let mut keeper_bonds = KeeperBonds::try_deploy(
+            env,
+            KeeperBondsInitArgs {
+                yscspr_token: yscspr.address(),
+            },
+        )?;
+        container.add_contract(&keeper_bonds)?;
+
+        keeper_bonds.set_reporter(validator_registry.address(), true);
+        keeper_bonds.set_reporter(liquid_staking.address(), true);
+        validator_registry.set_keeper_bonds(keeper_bonds.address());
+        liquid_staking.set_keeper_bonds(keeper_bonds.address());
+
         let stayer = StayerVault::try_deploy(
             env,
             StayerVaultInitArgs {
@@ -107,6 +121,7 @@ pub fn main() {
         .contract::<YSCSPR>()
         .contract::<ValidatorRegistry>()
         .contract::<LiquidStaking>()
+        .contract::<KeeperBonds>()
         .contract::<StayerVault>()
         .build()
         .run();
@@ -0,0 +1,3 @@
+pub fn main() {
+    odra_build::build();
+}
@@ -0,0 +1,626 @@
+use odra::casper_types::{U256, U512};
+use odra::prelude::*;
+
+const BASIS_POINTS: u64 = 10000;
+const ERA_DURATION_MS: u64 = 7_200_000;
+const DEFAULT_MIN_CSPR_BOND: u128 = 10_000_000_000_000;
+const DEFAULT_MIN_YSCSPR_BOND: u128 = 10_000_000_000_000;
+const DEFAULT_DISPUTE_WINDOW_MS: u64 = 4 * ERA_DURATION_MS;
+const DEFAULT_UNBONDING_DELAY_MS: u64 = 12 * ERA_DURATION_MS;
+const DEFAULT_SLASH_BPS: u64 = 5000;
+const DEFAULT_CHALLENGER_REWARD_BPS: u64 = 2000;
+
+#[odra::external_contract]
+pub trait YSCSPRContract {
+    fn transfer(&mut self, recipient: Address, amount: U256);
+    fn transfer_from(&mut self, owner: Address, recipient: Address, amount: U256);
+}
+
+#[odra::odra_type]
+pub struct BondConfig {
+    /// A keeper holding either minimum is bonded; zero disables that asset
+    pub min_cspr_bond: U512,
+    pub min_yscspr_bond: U256,
+    /// How long a report can be challenged, in milliseconds
+    pub dispute_window_ms: u64,
+    pub unbonding_delay_ms: u64,
+    /// Share of the reporter's bond taken when a challenge is upheld
+    pub slash_bps: u64,
+    /// Share of the slashed bond paid to the challenger
+    pub challenger_reward_bps: u64,
+}
+
+#[odra::odra_type]
+#[derive(Default)]
+pub struct KeeperBond {
+    pub cspr: U512,
+    pub yscspr: U256,
+    /// Leaving the bond, still slashable until withdrawn
+    pub unbonding_cspr: U512,
+    pub unbonding_yscspr: U256,
+    pub unlock_at: u64,
+}
+
+#[odra::odra_type]
+pub enum ReportStatus {
+    Open,
+    Challenged,
+    Upheld,
+    Dismissed,
+}
+
+/// A keeper report made through one of the protocol contracts
+#[odra::odra_type]
+pub struct Report {
+    /// Contract that received the report
+    pub source: Address,
+    pub reporter: Address,
+    pub era: u64,
+    pub recorded_at: u64,
+    pub status: ReportStatus,
+    pub challenger: Option<Address>,
+}
+
+/// Bonds that keepers lock to be trusted with reports
+/// Every `harvest_rewards` and `update_validators` report is logged here and
+/// can be challenged by another bonded keeper or the owner for the dispute
+/// window. The owner settles challenges; an upheld one slashes the reporter.
+/// Bonds stay locked while their owner has reports that can still be disputed.
+#[odra::module(events = [Bonded, UnbondRequested, Unbonded, ReportRecorded, ReportChallenged, ChallengeResolved, BondSlashed])]
+pub struct KeeperBonds {
+    owner: Var<Address>,
+    yscspr_token: External<YSCSPRContractContractRef>,
+    config: Var<BondConfig>,
+
+    bonds: Mapping<Address, KeeperBond>,
+
+    // Contracts allowed to log reports
+    reporters: Mapping<Address, bool>,
+    reports: Mapping<u64, Report>,
+    next_report_id: Var<u64>,
+    last_report_at: Mapping<Address, u64>,
+    open_challenges: Mapping<Address, u32>,
+
+    // Slashed bonds not paid to challengers
+    slashed_cspr: Var<U512>,
+    slashed_yscspr: Var<U256>,
+}
+
+#[odra::module]
+impl KeeperBonds {
+    #[odra(init)]
+    pub fn init(&mut self, yscspr_token: Address) {
+        self.owner.set(self.env().caller());
+        self.yscspr_token.set(yscspr_token);
+        self.config.set(BondConfig {
+            min_cspr_bond: U512::from(DEFAULT_MIN_CSPR_BOND),
+            min_yscspr_bond: U256::from(DEFAULT_MIN_YSCSPR_BOND),
+            dispute_window_ms: DEFAULT_DISPUTE_WINDOW_MS,
+            unbonding_delay_ms: DEFAULT_UNBONDING_DELAY_MS,
+            slash_bps: DEFAULT_SLASH_BPS,
+            challenger_reward_bps: DEFAULT_CHALLENGER_REWARD_BPS,
+        });
+        self.next_report_id.set(1);
+        self.slashed_cspr.set(U512::zero());
+        self.slashed_yscspr.set(U256::zero());
+    }
+
+    /// Locks the attached CSPR in the caller's bond
+    #[odra(payable)]
+    pub fn bond(&mut self) {
+        let amount = self.env().attached_value();
+        if amount.is_zero() {
+            self.env().revert(Error::InvalidAmount);
+        }
+
+        let keeper = self.env().caller();
+        let mut bond = self.get_bond(keeper);
+        bond.cspr = bond.cspr.saturating_add(amount);
+        self.bonds.set(&keeper, bond);
+        self.emit_bonded(keeper);
+    }
+
+    /// Locks `amount` ySCSPR in the caller's bond; needs an allowance first
+    pub fn bond_yscspr(&mut self, amount: U256) {
+        if amount.is_zero() {
+            self.env().revert(Error::InvalidAmount);
+        }
+
+        let keeper = self.env().caller();
+        let this = self.env().self_address();
+        self.yscspr_token.transfer_from(keeper, this, amount);
+
+        let mut bond = self.get_bond(keeper);
+        bond.yscspr = bond.yscspr.saturating_add(amount);
+        self.bonds.set(&keeper, bond);
+        self.emit_bonded(keeper);
+    }
+
+    /// Starts unbonding part of the caller's bond
+    /// The keeper loses the role as soon as what is left drops below the minimum.
+    pub fn request_unbond(&mut self, cspr: U512, yscspr: U256) {
+        let keeper = self.env().caller();
+        let mut bond = self.get_bond(keeper);
+        if (cspr.is_zero() && yscspr.is_zero()) || cspr > bond.cspr || yscspr > bond.yscspr {
+            self.env().revert(Error::InvalidAmount);
+        }
+
+        bond.cspr = bond.cspr.saturating_sub(cspr);
+        bond.yscspr = bond.yscspr.saturating_sub(yscspr);
+        bond.unbonding_cspr = bond.unbonding_cspr.saturating_add(cspr);
+        bond.unbonding_yscspr = bond.unbonding_yscspr.saturating_add(yscspr);
+        bond.unlock_at = self.env().get_block_time() + self.get_config().unbonding_delay_ms;
+        let unlock_at = bond.unlock_at;
+        self.bonds.set(&keeper, bond);
+
+        self.env().emit_event(UnbondRequested {
+            keeper,
+            cspr,
+            yscspr,
+            unlock_at,
+        });
+    }
+
+    /// Pays out the caller's unbonded CSPR and ySCSPR
+    /// Waits for the unbonding delay, for the dispute window of the keeper's
+    /// last report, and for any open challenge against them to be settled.
+    pub fn withdraw_unbonded(&mut self) {
+        let keeper = self.env().caller();
+        let mut bond = self.get_bond(keeper);
+        if bond.unbonding_cspr.is_zero() && bond.unbonding_yscspr.is_zero() {
+            self.env().revert(Error::NothingToWithdraw);
+        }
+
+        let now = self.env().get_block_time();
+        let disputable_until = self.last_report_at.get_or_default(&keeper)
+            .saturating_add(self.get_config().dispute_window_ms);
+        if now < bond.unlock_at || now < disputable_until || self.open_challenges.get_or_default(&keeper) > 0 {
+            self.env().revert(Error::BondLocked);
+        }
+
+        let (cspr, yscspr) = (bond.unbonding_cspr, bond.unbonding_yscspr);
+        bond.unbonding_cspr = U512::zero();
+        bond.unbonding_yscspr = U256::zero();
+        self.bonds.set(&keeper, bond);
+
+        if !cspr.is_zero() {
+            self.env().transfer_tokens(&keeper, &cspr);
+        }
+        if !yscspr.is_zero() {
+            self.yscspr_token.transfer(keeper, yscspr);
+        }
+
+        self.env().emit_event(Unbonded { keeper, cspr, yscspr });
+    }
+
+    pub fn is_bonded(&self, keeper: Address) -> bool {
+        let config = self.get_config();
+        let bond = self.get_bond(keeper);
+
+        (!config.min_cspr_bond.is_zero() && bond.cspr >= config.min_cspr_bond)
+            || (!config.min_yscspr_bond.is_zero() && bond.yscspr >= config.min_yscspr_bond)
+    }
+
+    /// Logs a report by `reporter` and returns its id; registered contracts only
+    pub fn record_report(&mut self, reporter: Address, era: u64) -> u64 {
+        let source = self.env().caller();
+        if !self.reporters.get_or_default(&source) {
+            self.env().revert(Error::Unauthorized);
+        }
+
+        let report_id = self.next_report_id.get_or_default();
+        self.next_report_id.set(report_id + 1);
+
+        let now = self.env().get_block_time();
+        self.reports.set(&report_id, Report {
+            source,
+            reporter,
+            era,
+            recorded_at: now,
+            status: ReportStatus::Open,
+            challenger: None,
+        });
+        self.last_report_at.set(&reporter, now);
+
+        self.env().emit_event(ReportRecorded {
+            report_id,
+            source,
+            reporter,
+            era,
+        });
+
+        report_id
+    }
+
+    /// Disputes a report within its window; bonded keepers and the owner only
+    pub fn challenge(&mut self, report_id: u64) {
+        let challenger = self.env().caller();
+        if challenger != self.owner() && !self.is_bonded(challenger) {
+            self.env().revert(Error::Unauthorized);
+        }
+
+        let mut report = self.get_report(report_id)
+            .unwrap_or_revert_with(&self.env(), Error::ReportNotFound);
+        if report.reporter == challenger {
+            self.env().revert(Error::SelfChallenge);
+        }
+        if report.status != ReportStatus::Open {
+            self.env().revert(Error::ReportNotOpen);
+        }
+        let deadline = report.recorded_at.saturating_add(self.get_config().dispute_window_ms);
+        if self.env().get_block_time() > deadline {
+            self.env().revert(Error::DisputeWindowClosed);
+        }
+
+        report.status = ReportStatus::Challenged;
+        report.challenger = Some(challenger);
+        let reporter = report.reporter;
+        self.reports.set(&report_id, report);
+        self.open_challenges.set(&reporter, self.open_challenges.get_or_default(&reporter) + 1);
+
+        self.env().emit_event(ReportChallenged {
+            report_id,
+            reporter,
+            challenger,
+        });
+    }
+
+    /// Owner settles a challenge; upholding it slashes the reporter's bond
+    pub fn resolve_challenge(&mut self, report_id: u64, upheld: bool) {
+        self.require_owner();
+
+        let mut report = self.get_report(report_id)
+            .unwrap_or_revert_with(&self.env(), Error::ReportNotFound);
+        if report.status != ReportStatus::Challenged {
+            self.env().revert(Error::NotChallenged);
+        }
+
+        let reporter = report.reporter;
+        let challenger = report.challenger.unwrap_or_revert_with(&self.env(), Error::NotChallenged);
+        report.status = if upheld { ReportStatus::Upheld } else { ReportStatus::Dismissed };
+        self.reports.set(&report_id, report);
+        let open = self.open_challenges.get_or_default(&reporter);
+        self.open_challenges.set(&reporter, open.saturating_sub(1));
+
+        let (slashed_cspr, slashed_yscspr) = if upheld {
+            self.slash(reporter, challenger)
+        } else {
+            (U512::zero(), U256::zero())
+        };
+
+        self.env().emit_event(ChallengeResolved {
+            report_id,
+            upheld,
+            slashed_cspr,
+            slashed_yscspr,
+        });
+    }
+
+    /// Sends slashed funds not paid to challengers to `recipient`
+    pub fn withdraw_slashed(&mut self, recipient: Address) {
+        self.require_owner();
+
+        let cspr = self.slashed_cspr.get_or_default();
+        let yscspr = self.slashed_yscspr.get_or_default();
+        self.slashed_cspr.set(U512::zero());
+        self.slashed_yscspr.set(U256::zero());
+
+        if !cspr.is_zero() {
+            self.env().transfer_tokens(&recipient, &cspr);
+        }
+        if !yscspr.is_zero() {
+            self.yscspr_token.transfer(recipient, yscspr);
+        }
+    }
+
+    pub fn set_reporter(&mut self, source: Address, allowed: bool) {
+        self.require_owner();
+        self.reporters.set(&source, allowed);
+    }
+
+    pub fn set_config(&mut self, config: BondConfig) {
+        self.require_owner();
+        if (config.min_cspr_bond.is_zero() && config.min_yscspr_bond.is_zero())
+            || config.slash_bps > BASIS_POINTS
+            || config.challenger_reward_bps > BASIS_POINTS
+            || config.unbonding_delay_ms < config.dispute_window_ms
+        {
+            self.env().revert(Error::InvalidConfig);
+        }
+        self.config.set(config);
+    }
+
+    pub fn get_config(&self) -> BondConfig {
+        self.config.get_or_revert_with(Error::NotInitialized)
+    }
+
+    pub fn get_bond(&self, keeper: Address) -> KeeperBond {
+        self.bonds.get(&keeper).unwrap_or_default()
+    }
+
+    pub fn get_report(&self, report_id: u64) -> Option<Report> {
+        self.reports.get(&report_id)
+    }
+
+    pub fn get_open_challenges(&self, keeper: Address) -> u32 {
+        self.open_challenges.get_or_default(&keeper)
+    }
+
+    pub fn is_reporter(&self, source: Address) -> bool {
+        self.reporters.get_or_default(&source)
+    }
+
+    fn slash(&mut self, reporter: Address, challenger: Address) -> (U512, U256) {
+        let config = self.get_config();
+        let mut bond = self.get_bond(reporter);
+
+        let cspr_cut = |amount: U512| amount.saturating_mul(U512::from(config.slash_bps)) / U512::from(BASIS_POINTS);
+        let yscspr_cut = |amount: U256| amount.saturating_mul(U256::from(config.slash_bps)) / U256::from(BASIS_POINTS);
+
+        let (active_cspr, unbonding_cspr) = (cspr_cut(bond.cspr), cspr_cut(bond.unbonding_cspr));
+        let (active_yscspr, unbonding_yscspr) = (yscspr_cut(bond.yscspr), yscspr_cut(bond.unbonding_yscspr));
+        bond.cspr = bond.cspr.saturating_sub(active_cspr);
+        bond.unbonding_cspr = bond.unbonding_cspr.saturating_sub(unbonding_cspr);
+        bond.yscspr = bond.yscspr.saturating_sub(active_yscspr);
+        bond.unbonding_yscspr = bond.unbonding_yscspr.saturating_sub(unbonding_yscspr);
+        self.bonds.set(&reporter, bond);
+
+        let cspr = active_cspr.saturating_add(unbonding_cspr);
+        let yscspr = active_yscspr.saturating_add(unbonding_yscspr);
+        let cspr_reward = cspr.saturating_mul(U512::from(config.challenger_reward_bps)) / U512::from(BASIS_POINTS);
+        let yscspr_reward = yscspr.saturating_mul(U256::from(config.challenger_reward_bps)) / U256::from(BASIS_POINTS);
+
+        self.slashed_cspr.set(self.slashed_cspr.get_or_default().saturating_add(cspr.saturating_sub(cspr_reward)));
+        self.slashed_yscspr.set(self.slashed_yscspr.get_or_default().saturating_add(yscspr.saturating_sub(yscspr_reward)));
+        if !cspr_reward.is_zero() {
+            self.env().transfer_tokens(&challenger, &cspr_reward);
+        }
+        if !yscspr_reward.is_zero() {
+            self.yscspr_token.transfer(challenger, yscspr_reward);
+        }
+
+        self.env().emit_event(BondSlashed {
+            keeper: reporter,
+            cspr,
+            yscspr,
+            challenger,
+        });
+
+        (cspr, yscspr)
+    }
+
+    fn emit_bonded(&self, keeper: Address) {
+        let bond = self.get_bond(keeper);
+        self.env().emit_event(Bonded {
+            keeper,
+            cspr: bond.cspr,
+            yscspr: bond.yscspr,
+        });
+    }
+
+    fn owner(&self) -> Address {
+        self.owner.get_or_revert_with(Error::NotInitialized)
+    }
+
+    fn require_owner(&self) {
+        if self.env().caller() != self.owner() {
+            self.env().revert(Error::Unauthorized);
+        }
+    }
+}
+
+/// Bond totals after a deposit
+#[odra::event]
+pub struct Bonded {
+    pub keeper: Address,
+    pub cspr: U512,
+    pub yscspr: U256,
+}
+
+#[odra::event]
+pub struct UnbondRequested {
+    pub keeper: Address,
+    pub cspr: U512,
+    pub yscspr: U256,
+    pub unlock_at: u64,
+}
+
+#[odra::event]
+pub struct Unbonded {
+    pub keeper: Address,
+    pub cspr: U512,
+    pub yscspr: U256,
+}
+
+#[odra::event]
+pub struct ReportRecorded {
+    pub report_id: u64,
+    pub source: Address,
+    pub reporter: Address,
+    pub era: u64,
+}
+
+#[odra::event]
+pub struct ReportChallenged {
+    pub report_id: u64,
+    pub reporter: Address,
+    pub challenger: Address,
+}
+
+#[odra::event]
+pub struct ChallengeResolved {
+    pub report_id: u64,
+    pub upheld: bool,
+    pub slashed_cspr: U512,
+    pub slashed_yscspr: U256,
+}
+
+#[odra::event]
+pub struct BondSlashed {
+    pub keeper: Address,
+    pub cspr: U512,
+    pub yscspr: U256,
+    pub challenger: Address,
+}
+
+#[odra::odra_error]
+pub enum Error {
+    Unauthorized = 1,
+    NotInitialized = 2,
+    InvalidAmount = 3,
+    InvalidConfig = 4,
+    NothingToWithdraw = 5,
+    BondLocked = 6,
+    ReportNotFound = 7,
+    ReportNotOpen = 8,
+    NotChallenged = 9,
+    DisputeWindowClosed = 10,
+    SelfChallenge = 11,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use odra::host::{Deployer, HostEnv, HostRef, NoArgs};
+
+    #[odra::module]
+    pub struct MockToken {
+        balances: Mapping<Address, U256>,
+    }
+
+    #[odra::module]
+    impl MockToken {
+        pub fn mint(&mut self, to: Address, amount: U256) {
+            let balance = self.balances.get_or_default(&to);
+            self.balances.set(&to, balance + amount);
+        }
+
+        pub fn balance_of(&self, address: Address) -> U256 {
+            self.balances.get_or_default(&address)
+        }
+
+        pub fn transfer(&mut self, recipient: Address, amount: U256) {
+            let caller = self.env().caller();
+            self.move_tokens(caller, recipient, amount);
+        }
+
+        pub fn transfer_from(&mut self, owner: Address, recipient: Address, amount: U256) {
+            self.move_tokens(owner, recipient, amount);
+        }
+
+        fn move_tokens(&mut self, from: Address, to: Address, amount: U256) {
+            let balance = self.balances.get_or_default(&from);
+            self.balances.set(&from, balance - amount);
+            let balance = self.balances.get_or_default(&to);
+            self.balances.set(&to, balance + amount);
+        }
+    }
+
+    fn setup() -> (HostEnv, KeeperBondsHostRef, MockTokenHostRef) {
+        let env = odra_test::env();
+        env.set_caller(env.get_account(0));
+
+        let token = MockToken::deploy(&env, NoArgs);
+        let mut bonds = KeeperBonds::deploy(&env, KeeperBondsInitArgs {
+            yscspr_token: token.address(),
+        });
+        // Account 9 stands in for a protocol contract that logs reports
+        bonds.set_reporter(env.get_account(9), true);
+
+        (env, bonds, token)
+    }
+
+    fn cspr(amount: u64) -> U512 {
+        U512::from(amount) * U512::from(1_000_000_000u64)
+    }
+
+    #[test]
+    fn test_bonding_grants_role_and_unbonding_waits() {
+        let (env, mut bonds, mut token) = setup();
+        let (alice, bob) = (env.get_account(1), env.get_account(2));
+
+        env.set_caller(alice);
+        bonds.with_tokens(cspr(5_000)).bond();
+        assert!(!bonds.is_bonded(alice));
+        bonds.with_tokens(cspr(5_000)).bond();
+        assert!(bonds.is_bonded(alice));
+        assert!(env.emitted(&bonds, "Bonded"));
+
+        token.mint(bob, U256::from(cspr(10_000).as_u128()));
+        env.set_caller(bob);
+        bonds.bond_yscspr(U256::from(cspr(10_000).as_u128()));
+        assert!(bonds.is_bonded(bob));
+        assert_eq!(token.balance_of(bonds.address()), U256::from(cspr(10_000).as_u128()));
+
+        // A report keeps the bond locked through its dispute window
+        env.set_caller(env.get_account(9));
+        bonds.record_report(alice, 5);
+        env.set_caller(alice);
+        bonds.request_unbond(cspr(1_000), U256::zero());
+        assert!(!bonds.is_bonded(alice));
+        env.advance_block_time(DEFAULT_DISPUTE_WINDOW_MS);
+        assert_eq!(bonds.try_withdraw_unbonded(), Err(Error::BondLocked.into()));
+
+        env.advance_block_time(DEFAULT_UNBONDING_DELAY_MS);
+        let before = env.balance_of(&alice);
+        bonds.withdraw_unbonded();
+        assert_eq!(env.balance_of(&alice), before + cspr(1_000));
+        assert_eq!(bonds.get_bond(alice).cspr, cspr(9_000));
+    }
+
+    #[test]
+    fn test_upheld_challenge_slashes_reporter() {
+        let (env, mut bonds, _token) = setup();
+        let owner = env.get_account(0);
+        let (alice, bob, carol) = (env.get_account(1), env.get_account(2), env.get_account(3));
+
+        env.set_caller(alice);
+        bonds.with_tokens(cspr(10_000)).bond();
+        env.set_caller(bob);
+        bonds.with_tokens(cspr(10_000)).bond();
+
+        env.set_caller(alice);
+        assert_eq!(bonds.try_record_report(alice, 5), Err(Error::Unauthorized.into()));
+        env.set_caller(env.get_account(9));
+        let report_id = bonds.record_report(alice, 5);
+        let second = bonds.record_report(alice, 6);
+
+        // Only bonded keepers or the owner can challenge, never the reporter
+        env.set_caller(carol);
+        assert_eq!(bonds.try_challenge(report_id), Err(Error::Unauthorized.into()));
+        env.set_caller(alice);
+        assert_eq!(bonds.try_challenge(report_id), Err(Error::SelfChallenge.into()));
+        env.set_caller(bob);
+        bonds.challenge(report_id);
+        assert!(env.emitted(&bonds, "ReportChallenged"));
+        assert_eq!(bonds.get_open_challenges(alice), 1);
+
+        // Alice cannot run from the challenge
+        env.set_caller(alice);
+        bonds.request_unbond(cspr(10_000), U256::zero());
+        env.advance_block_time(DEFAULT_UNBONDING_DELAY_MS);
+        assert_eq!(bonds.try_withdraw_unbonded(), Err(Error::BondLocked.into()));
+
+        env.set_caller(bob);
+        assert_eq!(bonds.try_challenge(second), Err(Error::DisputeWindowClosed.into()));
+        assert_eq!(bonds.try_resolve_challenge(report_id, true), Err(Error::Unauthorized.into()));
+
+        let bob_before = env.balance_of(&bob);
+        env.set_caller(owner);
+        bonds.resolve_challenge(report_id, true);
+        assert_eq!(bonds.get_report(report_id).unwrap().status, ReportStatus::Upheld);
+        assert!(env.emitted(&bonds, "BondSlashed"));
+
+        // Half the bond is slashed, a fifth of that goes to the challenger
+        assert_eq!(bonds.get_bond(alice).unbonding_cspr, cspr(5_000));
+        assert_eq!(env.balance_of(&bob), bob_before + cspr(1_000));
+
+        let owner_before = env.balance_of(&owner);
+        bonds.withdraw_slashed(owner);
+        assert_eq!(env.balance_of(&owner), owner_before + cspr(4_000));
+
+        env.set_caller(alice);
+        bonds.withdraw_unbonded();
+        assert!(bonds.get_bond(alice).unbonding_cspr.is_zero());
+    }
+}
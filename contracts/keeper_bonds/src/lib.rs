@@ -0,0 +1,7 @@
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), no_main)]
+
+extern crate alloc;
+
+pub mod keeper_bonds;
+pub use keeper_bonds::*;
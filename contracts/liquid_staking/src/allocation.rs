@@ -0,0 +1,223 @@
+use odra::prelude::*;
+use odra::casper_types::{PublicKey, U512};
+
+use crate::liquid_staking::{Error, Evacuation, PendingRedelegation, StakeLot, StakeMove, BASIS_POINTS};
+use crate::queue::IdList;
+
+// Shortlist stake routing looks at, so stake gas does not grow with the set
+pub(crate) const MAX_STAKE_CANDIDATES: usize = 16;
+
+/// Redelegations the keeper has started toward the score-weighted target
+/// In-flight amounts are tracked per validator so the rebalance plan treats
+/// them as already moved and never proposes the same move twice. Once stake
+/// has moved it is logged per source validator, and user positions replay
+/// the log the next time they are touched.
+#[odra::module]
+pub struct Allocation {
+    // Actual stake within this share of the pool counts as on target
+    tolerance_bps: Var<u64>,
+
+    redelegations: Mapping<u64, PendingRedelegation>,
+    next_redelegation_id: Var<u64>,
+    open: SubModule<IdList>,
+    outgoing: Mapping<PublicKey, U512>,
+    incoming: Mapping<PublicKey, U512>,
+
+    // Where an unhealthy validator's stake went, for migrating users later
+    evacuations: Mapping<PublicKey, Option<Evacuation>>,
+
+    // Best-scored healthy validators and the score of all of them, as of the last refresh
+    candidates: Var<Vec<(PublicKey, u64)>>,
+    candidate_score: Var<u64>,
+
+    moves: Mapping<(PublicKey, u32), StakeMove>,
+    move_count: Mapping<PublicKey, u32>,
+    // Moves logged on any validator, giving them one order
+    move_seq: Var<u64>,
+    // How many of a validator's moves each user position already reflects
+    settled_moves: Mapping<(Address, PublicKey), u32>,
+    // Each user's stake still being replayed through the log
+    lots: Mapping<Address, Vec<StakeLot>>,
+}
+
+#[odra::module]
+impl Allocation {
+    pub fn init(&mut self, tolerance_bps: u64) {
+        self.tolerance_bps.set(tolerance_bps);
+        self.next_redelegation_id.set(1);
+    }
+
+    pub fn tolerance_bps(&self) -> u64 {
+        self.tolerance_bps.get_or_default()
+    }
+
+    pub fn set_tolerance_bps(&mut self, tolerance_bps: u64) {
+        if tolerance_bps > BASIS_POINTS {
+            self.env().revert(Error::InvalidConfig);
+        }
+        self.tolerance_bps.set(tolerance_bps);
+    }
+
+    /// Validator stake once open redelegations land
+    pub fn effective_stake(&self, validator: &PublicKey, stake: U512) -> U512 {
+        stake
+            .saturating_sub(self.outgoing.get_or_default(validator))
+            .saturating_add(self.incoming.get_or_default(validator))
+    }
+
+    pub fn start(&mut self, from: PublicKey, to: PublicKey, amount: U512, era: u64) -> u64 {
+        let id = self.next_redelegation_id.get_or_default();
+        self.next_redelegation_id.set(id + 1);
+
+        self.outgoing.add(&from, amount);
+        self.incoming.add(&to, amount);
+        self.redelegations.set(&id, PendingRedelegation { id, from, to, amount, era });
+        self.open.push_back(id);
+
+        id
+    }
+
+    /// Closes an open redelegation and returns it
+    pub fn finish(&mut self, id: u64) -> PendingRedelegation {
+        let redelegation = self.redelegations.get(&id)
+            .filter(|r| !r.amount.is_zero())
+            .unwrap_or_revert_with(&self.env(), Error::RedelegationNotFound);
+
+        let outgoing = self.outgoing.get_or_default(&redelegation.from);
+        self.outgoing.set(&redelegation.from, outgoing.saturating_sub(redelegation.amount));
+        let incoming = self.incoming.get_or_default(&redelegation.to);
+        self.incoming.set(&redelegation.to, incoming.saturating_sub(redelegation.amount));
+
+        let mut closed = redelegation.clone();
+        closed.amount = U512::zero();
+        self.redelegations.set(&id, closed);
+        self.open.remove(id);
+
+        redelegation
+    }
+
+    /// Logs `amount` leaving `from`, which held `base` just before
+    pub fn record_move(&mut self, from: &PublicKey, to: PublicKey, amount: U512, base: U512) {
+        let index = self.move_count(from);
+        let to_index = self.move_count(&to);
+        let seq = self.move_seq.get_or_default();
+        self.moves.set(&(from.clone(), index), StakeMove { to, amount, base, to_index, seq });
+        self.move_count.set(from, index + 1);
+        self.move_seq.set(seq + 1);
+    }
+
+    pub fn move_count(&self, validator: &PublicKey) -> u32 {
+        self.move_count.get_or_default(validator)
+    }
+
+    pub fn stake_move(&self, validator: &PublicKey, index: u32) -> StakeMove {
+        self.moves.get(&(validator.clone(), index))
+            .unwrap_or_revert_with(&self.env(), Error::RedelegationNotFound)
+    }
+
+    pub fn settled_moves(&self, user: Address, validator: &PublicKey) -> u32 {
+        self.settled_moves.get_or_default(&(user, validator.clone()))
+    }
+
+    pub fn set_settled_moves(&mut self, user: Address, validator: &PublicKey, count: u32) {
+        self.settled_moves.set(&(user, validator.clone()), count);
+    }
+
+    pub fn lots(&self, user: Address) -> Vec<StakeLot> {
+        self.lots.get_or_default(&user)
+    }
+
+    pub fn set_lots(&mut self, user: Address, lots: Vec<StakeLot>) {
+        self.lots.set(&user, lots);
+    }
+
+    pub fn evacuation(&self, validator: &PublicKey) -> Option<Evacuation> {
+        self.evacuations.get(validator).flatten()
+    }
+
+    /// Adds `destinations` and the redelegations carrying them to the
+    /// validator's evacuation record
+    pub fn record_evacuation(
+        &mut self,
+        validator: &PublicKey,
+        destinations: Vec<(PublicKey, U512)>,
+        redelegations: Vec<u64>,
+        era: u64,
+    ) {
+        let mut evacuation = self.evacuation(validator).unwrap_or(Evacuation {
+            era,
+            total: U512::zero(),
+            destinations: Vec::new(),
+            redelegations: Vec::new(),
+        });
+        evacuation.era = era;
+        evacuation.redelegations.extend(redelegations);
+
+        for (destination, amount) in destinations {
+            evacuation.total = evacuation.total.saturating_add(amount);
+            match evacuation.destinations.iter_mut().find(|(v, _)| *v == destination) {
+                Some((_, existing)) => *existing = existing.saturating_add(amount),
+                None => evacuation.destinations.push((destination, amount)),
+            }
+        }
+
+        self.evacuations.set(validator, Some(evacuation));
+    }
+
+    /// Takes a cancelled redelegation back out of its evacuation, if it was part of one
+    pub fn unrecord_evacuation(&mut self, redelegation: &PendingRedelegation) {
+        let mut evacuation = match self.evacuation(&redelegation.from) {
+            Some(evacuation) if evacuation.redelegations.contains(&redelegation.id) => evacuation,
+            _ => return,
+        };
+
+        evacuation.redelegations.retain(|id| *id != redelegation.id);
+        evacuation.total = evacuation.total.saturating_sub(redelegation.amount);
+        if let Some((_, sent)) = evacuation.destinations.iter_mut().find(|(v, _)| *v == redelegation.to) {
+            *sent = sent.saturating_sub(redelegation.amount);
+        }
+        evacuation.destinations.retain(|(_, sent)| !sent.is_zero());
+
+        self.evacuations.set(&redelegation.from, Some(evacuation));
+    }
+
+    /// Stake may be routed to the validator again
+    pub fn clear_evacuation(&mut self, validator: &PublicKey) {
+        self.evacuations.set(validator, None);
+    }
+
+    pub fn candidates(&self) -> Vec<(PublicKey, u64)> {
+        self.candidates.get_or_default()
+    }
+
+    /// Total score of every healthy validator, shortlisted or not
+    pub fn candidate_score(&self) -> u64 {
+        self.candidate_score.get_or_default()
+    }
+
+    /// Keeps the best-scored of `scored` as the routing shortlist
+    /// Returns how many made it.
+    pub fn set_candidates(&mut self, scored: Vec<(PublicKey, u64)>) -> u32 {
+        let total = scored.iter().fold(0u64, |sum, (_, score)| sum.saturating_add(*score));
+        let mut scored = scored;
+        scored.sort_by(|a, b| b.1.cmp(&a.1));
+        scored.truncate(MAX_STAKE_CANDIDATES);
+
+        let count = scored.len() as u32;
+        self.candidates.set(scored);
+        self.candidate_score.set(total);
+        count
+    }
+
+    pub fn open_count(&self) -> u64 {
+        self.open.len()
+    }
+
+    pub fn open_page(&self, offset: u64, limit: u64) -> Vec<PendingRedelegation> {
+        self.open
+            .page(offset, limit)
+            .into_iter()
+            .filter_map(|id| self.redelegations.get(&id))
+            .collect()
+    }
+}
@@ -0,0 +1,132 @@
+use odra::prelude::*;
+use odra::casper_types::{PublicKey, U512};
+
+use crate::liquid_staking::{CapMode, Error, BASIS_POINTS};
+
+/// Limits on how much of the pool a single validator may hold, and the
+/// smallest delegation the network accepts for it
+/// The share cap only applies once the pool reaches `share_floor`, otherwise
+/// the first stakes could never land. Capacity is the most a validator can
+/// take from us, as reported from its delegator limits.
+#[odra::module]
+pub struct StakeCaps {
+    mode: Var<CapMode>,
+    max_share_bps: Var<u64>,
+    share_floor: Var<U512>,
+    capacity: Mapping<PublicKey, U512>,
+
+    min_delegation: Var<U512>,
+    min_delegation_override: Mapping<PublicKey, U512>,
+    // Send stakes that would sit below the minimum to a validator that can take them
+    reroute_small: Var<bool>,
+
+    // Every validator that has received stake, candidates for redirects
+    validators: List<PublicKey>,
+    known: Mapping<PublicKey, bool>,
+}
+
+#[odra::module]
+impl StakeCaps {
+    pub fn init(&mut self, min_delegation: U512) {
+        self.mode.set(CapMode::Revert);
+        self.max_share_bps.set(BASIS_POINTS);
+        self.share_floor.set(U512::zero());
+        self.min_delegation.set(min_delegation);
+        self.reroute_small.set(false);
+    }
+
+    pub fn mode(&self) -> CapMode {
+        self.mode.get().unwrap_or(CapMode::Revert)
+    }
+
+    pub fn set_mode(&mut self, mode: CapMode) {
+        self.mode.set(mode);
+    }
+
+    pub fn max_share_bps(&self) -> u64 {
+        self.max_share_bps.get_or_default()
+    }
+
+    pub fn share_floor(&self) -> U512 {
+        self.share_floor.get_or_default()
+    }
+
+    pub fn set_share_limit(&mut self, max_share_bps: u64, share_floor: U512) {
+        if max_share_bps == 0 || max_share_bps > BASIS_POINTS {
+            self.env().revert(Error::InvalidConfig);
+        }
+        self.max_share_bps.set(max_share_bps);
+        self.share_floor.set(share_floor);
+    }
+
+    /// None means the validator has no known capacity limit
+    pub fn capacity(&self, validator: &PublicKey) -> Option<U512> {
+        self.capacity.get(validator).filter(|cap| !cap.is_zero())
+    }
+
+    pub fn set_capacity(&mut self, validator: PublicKey, capacity: U512) {
+        self.capacity.set(&validator, capacity);
+    }
+
+    pub fn min_delegation(&self, validator: &PublicKey) -> U512 {
+        self.min_delegation_override.get(validator)
+            .filter(|min| !min.is_zero())
+            .unwrap_or_else(|| self.min_delegation.get_or_default())
+    }
+
+    pub fn set_min_delegation(&mut self, min_delegation: U512) {
+        self.min_delegation.set(min_delegation);
+    }
+
+    /// Zero falls back to the default minimum
+    pub fn set_validator_min_delegation(&mut self, validator: PublicKey, min_delegation: U512) {
+        self.min_delegation_override.set(&validator, min_delegation);
+    }
+
+    pub fn reroute_small(&self) -> bool {
+        self.reroute_small.get_or_default()
+    }
+
+    pub fn set_reroute_small(&mut self, reroute: bool) {
+        self.reroute_small.set(reroute);
+    }
+
+    /// Whether `amount` more on a validator holding `validator_stake` stays
+    /// within both caps, given the pool currently holds `total_staked`
+    pub fn fits(
+        &self,
+        validator: &PublicKey,
+        validator_stake: U512,
+        amount: U512,
+        total_staked: U512,
+    ) -> bool {
+        let new_stake = validator_stake.saturating_add(amount);
+        if let Some(capacity) = self.capacity(validator) {
+            if new_stake > capacity {
+                return false;
+            }
+        }
+
+        let new_total = total_staked.saturating_add(amount);
+        if new_total < self.share_floor() {
+            return true;
+        }
+        new_stake.saturating_mul(U512::from(BASIS_POINTS))
+            <= new_total.saturating_mul(U512::from(self.max_share_bps()))
+    }
+
+    pub fn track(&mut self, validator: &PublicKey) {
+        if !self.known.get_or_default(validator) {
+            self.known.set(validator, true);
+            self.validators.push(validator.clone());
+        }
+    }
+
+    pub fn validator_count(&self) -> u32 {
+        self.validators.len()
+    }
+
+    pub fn validator_at(&self, index: u32) -> Option<PublicKey> {
+        self.validators.get(index)
+    }
+}
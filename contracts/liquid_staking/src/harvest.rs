@@ -0,0 +1,210 @@
+use odra::prelude::*;
+use odra::casper_types::{U256, U512};
+
+use crate::liquid_staking::{
+    Error, PendingHarvest, RateSnapshot, BASIS_POINTS, ERAS_PER_YEAR, ERA_DURATION_MS, MAX_VESTING_PERIOD_MS,
+};
+
+/// Harvest bookkeeping for LiquidStaking
+/// Bounds each keeper report by a maximum APR over the block time since the
+/// last one and parks anything above it until the guardian reviews it.
+/// Accepted rewards vest linearly over `vesting_period` so the exchange rate
+/// never jumps in one step. Every recorded harvest also snapshots the
+/// exchange rate for analytics.
+#[odra::module]
+pub struct HarvestLedger {
+    last_harvest_era: Var<u64>,
+    // Block time the APR bound is measured from; the keeper's era is not trusted for it
+    last_harvest_time: Var<u64>,
+    guardian: Var<Address>,
+    max_apr_bps: Var<u64>,
+    pending: Var<Option<PendingHarvest>>,
+    // Era and block time of the last accepted report; unset until the first one
+    last_report_era: Var<u64>,
+    last_report_time: Var<u64>,
+
+    // Linear reward stream, block time in milliseconds
+    vesting_period: Var<u64>,
+    vesting_amount: Var<U512>,
+    vesting_start: Var<u64>,
+    vesting_end: Var<u64>,
+
+    cumulative_rewards: Var<U512>,
+    // Fee the protocol kept after referrers' cut, compounding on validators
+    protocol_fees: Var<U512>,
+    // Ascending by era, one entry per recorded harvest
+    rate_history: List<RateSnapshot>,
+}
+
+#[odra::module]
+impl HarvestLedger {
+    pub fn init(&mut self, guardian: Address, max_apr_bps: u64, vesting_period: u64, now: u64) {
+        self.last_harvest_era.set(0);
+        self.last_harvest_time.set(now);
+        self.guardian.set(guardian);
+        self.max_apr_bps.set(max_apr_bps);
+        self.pending.set(None);
+        self.vesting_period.set(vesting_period);
+        self.vesting_amount.set(U512::zero());
+        self.vesting_start.set(0);
+        self.vesting_end.set(0);
+        self.cumulative_rewards.set(U512::zero());
+    }
+
+    pub fn last_harvest_era(&self) -> u64 {
+        self.last_harvest_era.get_or_default()
+    }
+
+    /// Latest era a report at `now` may claim: the last reported era plus the
+    /// eras the block time has moved since, and one of slack
+    pub fn max_report_era(&self, now: u64) -> Option<u64> {
+        let era = self.last_report_era.get()?;
+        let elapsed = now.saturating_sub(self.last_report_time.get_or_default());
+        Some(era.saturating_add(elapsed / ERA_DURATION_MS).saturating_add(1))
+    }
+
+    pub fn record_report(&mut self, era: u64, now: u64) {
+        self.last_report_era.set(era);
+        self.last_report_time.set(now);
+    }
+
+    pub fn record_harvest(&mut self, era: u64, exchange_rate: U256, now: u64) {
+        self.last_harvest_era.set(era);
+        self.last_harvest_time.set(now);
+        self.rate_history.push(RateSnapshot { era, exchange_rate });
+    }
+
+    /// Closes the reviewed window without recording a harvest, so a rejected
+    /// report's allowance cannot be claimed again by the next one
+    pub fn close_window(&mut self, now: u64) {
+        self.last_harvest_time.set(now);
+    }
+
+    pub fn guardian(&self) -> Address {
+        self.guardian.get_or_revert_with(Error::NotInitialized)
+    }
+
+    pub fn set_guardian(&mut self, guardian: Address) {
+        self.guardian.set(guardian);
+    }
+
+    pub fn max_apr_bps(&self) -> u64 {
+        self.max_apr_bps.get_or_default()
+    }
+
+    pub fn set_max_apr_bps(&mut self, apr_bps: u64) {
+        if apr_bps == 0 || apr_bps > BASIS_POINTS {
+            self.env().revert(Error::InvalidConfig);
+        }
+        self.max_apr_bps.set(apr_bps);
+    }
+
+    /// Largest reward a harvest at `now` may report
+    pub fn max_rewards(&self, principal: U512, now: u64) -> U512 {
+        let elapsed = now.saturating_sub(self.last_harvest_time.get_or_default());
+        let year_ms = ERAS_PER_YEAR.saturating_mul(ERA_DURATION_MS);
+        principal
+            .saturating_mul(U512::from(self.max_apr_bps()))
+            .saturating_mul(U512::from(elapsed))
+            / U512::from(BASIS_POINTS).saturating_mul(U512::from(year_ms))
+    }
+
+    pub fn pending(&self) -> Option<PendingHarvest> {
+        self.pending.get().flatten()
+    }
+
+    pub fn set_pending(&mut self, harvest: Option<PendingHarvest>) {
+        self.pending.set(harvest);
+    }
+
+    pub fn take_pending(&mut self) -> PendingHarvest {
+        let harvest = self.pending()
+            .unwrap_or_revert_with(&self.env(), Error::NoPendingHarvest);
+        self.pending.set(None);
+        harvest
+    }
+
+    pub fn vesting_period(&self) -> u64 {
+        self.vesting_period.get_or_default()
+    }
+
+    pub fn set_vesting_period(&mut self, period: u64) {
+        if period == 0 || period > MAX_VESTING_PERIOD_MS {
+            self.env().revert(Error::InvalidConfig);
+        }
+        self.vesting_period.set(period);
+    }
+
+    pub fn vesting_end(&self) -> u64 {
+        self.vesting_end.get_or_default()
+    }
+
+    /// Starts a new stream with `rewards` plus whatever the previous one
+    /// had not released yet
+    pub fn start_vesting(&mut self, rewards: U512, now: u64) {
+        let carried = self.unvested(now);
+
+        self.vesting_amount.set(carried.checked_add(rewards).unwrap_or_default());
+        self.vesting_start.set(now);
+        self.vesting_end.set(now.saturating_add(self.vesting_period()));
+    }
+
+    /// Rewards already counted in total_staked but not yet released
+    pub fn unvested(&self, now: u64) -> U512 {
+        let end = self.vesting_end.get_or_default();
+        if now >= end {
+            return U512::zero();
+        }
+
+        let start = self.vesting_start.get_or_default();
+        let amount = self.vesting_amount.get_or_default();
+
+        amount
+            .saturating_mul(U512::from(end - now))
+            / U512::from(end.saturating_sub(start).max(1))
+    }
+
+    pub fn cumulative_rewards(&self) -> U512 {
+        self.cumulative_rewards.get_or_default()
+    }
+
+    pub fn add_rewards(&mut self, rewards: U512) {
+        self.cumulative_rewards.add(rewards);
+    }
+
+    pub fn protocol_fees(&self) -> U512 {
+        self.protocol_fees.get_or_default()
+    }
+
+    pub fn add_protocol_fees(&mut self, fees: U512) {
+        self.protocol_fees.add(fees);
+    }
+
+    pub fn spend_protocol_fees(&mut self, amount: U512) {
+        self.protocol_fees.set(self.protocol_fees().saturating_sub(amount));
+    }
+
+    pub fn earliest_snapshot(&self) -> Option<RateSnapshot> {
+        self.rate_history.get(0)
+    }
+
+    pub fn latest_snapshot(&self) -> Option<RateSnapshot> {
+        self.rate_history.len().checked_sub(1).and_then(|i| self.rate_history.get(i))
+    }
+
+    /// Latest snapshot taken at or before `era`
+    pub fn snapshot_at(&self, era: u64) -> Option<RateSnapshot> {
+        let mut low = 0u32;
+        let mut high = self.rate_history.len();
+
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match self.rate_history.get(mid) {
+                Some(snapshot) if snapshot.era <= era => low = mid + 1,
+                _ => high = mid,
+            }
+        }
+
+        low.checked_sub(1).and_then(|i| self.rate_history.get(i))
+    }
+}
@@ -0,0 +1,143 @@
+use odra::prelude::*;
+use odra::casper_types::U512;
+
+use crate::liquid_staking::Error;
+
+/// Deposit limits for a gradual launch
+/// The per-stake bounds always apply. While launch mode is on, deposits also
+/// need an allowlisted beneficiary (if the allowlist is enabled) and must
+/// stay under the per-address cumulative cap and the global TVL cap; a zero
+/// cap means no limit.
+#[odra::module]
+pub struct LaunchGuard {
+    min_stake: Var<U512>,
+    max_single_stake: Var<U512>,
+
+    launch_mode: Var<bool>,
+    allowlist_enabled: Var<bool>,
+    allowlist: Mapping<Address, bool>,
+    address_cap: Var<U512>,
+    tvl_cap: Var<U512>,
+    // Everything an address has ever deposited, unstakes don't free room
+    deposited: Mapping<Address, U512>,
+}
+
+#[odra::module]
+impl LaunchGuard {
+    pub fn init(&mut self, min_stake: U512, max_single_stake: U512) {
+        self.set_stake_bounds(min_stake, max_single_stake);
+        self.launch_mode.set(false);
+        self.allowlist_enabled.set(false);
+        self.address_cap.set(U512::zero());
+        self.tvl_cap.set(U512::zero());
+    }
+
+    pub fn min_stake(&self) -> U512 {
+        self.min_stake.get_or_default()
+    }
+
+    pub fn max_single_stake(&self) -> U512 {
+        self.max_single_stake.get_or_default()
+    }
+
+    pub fn set_stake_bounds(&mut self, min_stake: U512, max_single_stake: U512) {
+        if min_stake.is_zero() || min_stake > max_single_stake {
+            self.env().revert(Error::InvalidConfig);
+        }
+        self.min_stake.set(min_stake);
+        self.max_single_stake.set(max_single_stake);
+    }
+
+    pub fn launch_mode(&self) -> bool {
+        self.launch_mode.get_or_default()
+    }
+
+    pub fn set_launch_mode(&mut self, enabled: bool) {
+        self.launch_mode.set(enabled);
+    }
+
+    pub fn allowlist_enabled(&self) -> bool {
+        self.allowlist_enabled.get_or_default()
+    }
+
+    pub fn set_allowlist_enabled(&mut self, enabled: bool) {
+        self.allowlist_enabled.set(enabled);
+    }
+
+    pub fn is_allowed(&self, account: Address) -> bool {
+        self.allowlist.get_or_default(&account)
+    }
+
+    pub fn set_allowed(&mut self, account: Address, allowed: bool) {
+        self.allowlist.set(&account, allowed);
+    }
+
+    pub fn address_cap(&self) -> U512 {
+        self.address_cap.get_or_default()
+    }
+
+    pub fn tvl_cap(&self) -> U512 {
+        self.tvl_cap.get_or_default()
+    }
+
+    pub fn set_caps(&mut self, address_cap: U512, tvl_cap: U512) {
+        self.address_cap.set(address_cap);
+        self.tvl_cap.set(tvl_cap);
+    }
+
+    pub fn deposited(&self, account: Address) -> U512 {
+        self.deposited.get_or_default(&account)
+    }
+
+    /// Checks a deposit of `amount` for `beneficiary` and records it
+    pub fn admit(&mut self, beneficiary: Address, amount: U512, total_staked: U512) {
+        if amount < self.min_stake() {
+            self.env().revert(Error::StakeTooLow);
+        }
+        if amount > self.max_single_stake() {
+            self.env().revert(Error::StakeTooHigh);
+        }
+
+        let deposited = self.deposited(beneficiary).saturating_add(amount);
+        self.deposited.set(&beneficiary, deposited);
+
+        if !self.launch_mode() {
+            return;
+        }
+        if self.allowlist_enabled() && !self.is_allowed(beneficiary) {
+            self.env().revert(Error::NotAllowlisted);
+        }
+        let address_cap = self.address_cap();
+        if !address_cap.is_zero() && deposited > address_cap {
+            self.env().revert(Error::AddressCapExceeded);
+        }
+        let tvl_cap = self.tvl_cap();
+        if !tvl_cap.is_zero() && total_staked.saturating_add(amount) > tvl_cap {
+            self.env().revert(Error::TvlCapExceeded);
+        }
+    }
+
+    /// Largest deposit `beneficiary` could make right now
+    pub fn max_deposit(&self, beneficiary: Address, total_staked: U512) -> U512 {
+        let mut max = self.max_single_stake();
+        if !self.launch_mode() {
+            return max;
+        }
+        if self.allowlist_enabled() && !self.is_allowed(beneficiary) {
+            return U512::zero();
+        }
+
+        let address_cap = self.address_cap();
+        if !address_cap.is_zero() {
+            max = max.min(address_cap.saturating_sub(self.deposited(beneficiary)));
+        }
+        let tvl_cap = self.tvl_cap();
+        if !tvl_cap.is_zero() {
+            max = max.min(tvl_cap.saturating_sub(total_staked));
+        }
+        if max < self.min_stake() {
+            return U512::zero();
+        }
+        max
+    }
+}
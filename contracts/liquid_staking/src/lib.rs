@@ -3,5 +3,15 @@
 
 extern crate alloc;
 
+pub mod allocation;
+pub mod caps;
+pub mod harvest;
+pub mod launch;
 pub mod liquid_staking;
+pub mod pending;
+pub mod queue;
+pub mod referrals;
+pub mod reserves;
+pub mod roles;
+pub mod withdrawals;
 pub use liquid_staking::*;
@@ -0,0 +1,130 @@
+use odra::prelude::*;
+use odra::casper_types::{PublicKey, U512};
+
+use crate::liquid_staking::PendingDelegation;
+
+/// Per-validator amounts waiting for the keeper
+/// Entries are keyed by validator so merging a new stake is a single write,
+/// and linked in arrival order so the keeper can page through them.
+#[odra::module]
+pub struct PendingBook {
+    entries: Mapping<PublicKey, PendingDelegation>,
+    next: Mapping<PublicKey, Option<PublicKey>>,
+    prev: Mapping<PublicKey, Option<PublicKey>>,
+    head: Var<Option<PublicKey>>,
+    tail: Var<Option<PublicKey>>,
+    len: Var<u32>,
+    total: Var<U512>,
+}
+
+#[odra::module]
+impl PendingBook {
+    pub fn len(&self) -> u32 {
+        self.len.get_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Sum of all pending amounts
+    pub fn total(&self) -> U512 {
+        self.total.get_or_default()
+    }
+
+    pub fn get(&self, validator: &PublicKey) -> Option<PendingDelegation> {
+        self.entries.get(validator).filter(|entry| !entry.amount.is_zero())
+    }
+
+    /// Adds `amount` to the validator's entry, appending it if new
+    pub fn add(&mut self, validator: PublicKey, amount: U512, era: u64) {
+        self.total.add(amount);
+
+        if let Some(mut entry) = self.get(&validator) {
+            entry.amount = entry.amount.checked_add(amount).unwrap_or_default();
+            self.entries.set(&validator, entry);
+            return;
+        }
+
+        let tail = self.tail.get().flatten();
+        match &tail {
+            Some(last) => self.next.set(last, Some(validator.clone())),
+            None => self.head.set(Some(validator.clone())),
+        }
+        self.prev.set(&validator, tail);
+        self.next.set(&validator, None);
+        self.tail.set(Some(validator.clone()));
+        self.len.set(self.len() + 1);
+
+        self.entries.set(&validator, PendingDelegation {
+            validator: validator.clone(),
+            amount,
+            era,
+        });
+    }
+
+    /// Takes `amount` off the validator's entry, dropping it once fully
+    /// processed. Returns false when the validator has nothing pending.
+    pub fn reduce(&mut self, validator: &PublicKey, amount: U512) -> bool {
+        let mut entry = match self.get(validator) {
+            Some(entry) => entry,
+            None => return false,
+        };
+
+        let processed = amount.min(entry.amount);
+        self.total.set(self.total().saturating_sub(processed));
+
+        if amount >= entry.amount {
+            self.unlink(validator);
+        } else {
+            entry.amount = entry.amount.checked_sub(amount).unwrap_or_default();
+            self.entries.set(validator, entry);
+        }
+        true
+    }
+
+    /// Up to `limit` entries after skipping `offset`, in arrival order
+    pub fn page(&self, offset: u32, limit: u32) -> Vec<PendingDelegation> {
+        let mut entries = Vec::new();
+        let mut cursor = self.head.get().flatten();
+        let mut index = 0u32;
+
+        while let Some(validator) = cursor {
+            if entries.len() as u32 >= limit {
+                break;
+            }
+            if index >= offset {
+                if let Some(entry) = self.get(&validator) {
+                    entries.push(entry);
+                }
+            }
+            index += 1;
+            cursor = self.next.get(&validator).flatten();
+        }
+
+        entries
+    }
+
+    fn unlink(&mut self, validator: &PublicKey) {
+        let prev = self.prev.get(validator).flatten();
+        let next = self.next.get(validator).flatten();
+
+        match &prev {
+            Some(p) => self.next.set(p, next.clone()),
+            None => self.head.set(next.clone()),
+        }
+        match &next {
+            Some(n) => self.prev.set(n, prev.clone()),
+            None => self.tail.set(prev.clone()),
+        }
+
+        self.next.set(validator, None);
+        self.prev.set(validator, None);
+        self.entries.set(validator, PendingDelegation {
+            validator: validator.clone(),
+            amount: U512::zero(),
+            era: 0,
+        });
+        self.len.set(self.len().saturating_sub(1));
+    }
+}
@@ -0,0 +1,129 @@
+use odra::prelude::*;
+
+/// FIFO of ids backed by a mapping
+/// Push and pop touch a single slot, so cost stays flat as the queue grows.
+#[odra::module]
+pub struct IdQueue {
+    items: Mapping<u64, u64>,
+    head: Var<u64>,
+    tail: Var<u64>,
+}
+
+#[odra::module]
+impl IdQueue {
+    pub fn len(&self) -> u64 {
+        self.tail.get_or_default() - self.head.get_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn push(&mut self, id: u64) {
+        let tail = self.tail.get_or_default();
+        self.items.set(&tail, id);
+        self.tail.set(tail + 1);
+    }
+
+    pub fn peek(&self) -> Option<u64> {
+        self.get(0)
+    }
+
+    pub fn pop(&mut self) -> Option<u64> {
+        let id = self.peek()?;
+        self.head.set(self.head.get_or_default() + 1);
+        Some(id)
+    }
+
+    /// Id at `offset` positions from the front
+    pub fn get(&self, offset: u64) -> Option<u64> {
+        if offset >= self.len() {
+            return None;
+        }
+        self.items.get(&(self.head.get_or_default() + offset))
+    }
+}
+
+/// Doubly linked list of non-zero ids backed by mappings
+/// Append and remove are O(1); 0 marks the end of the list.
+#[odra::module]
+pub struct IdList {
+    next: Mapping<u64, u64>,
+    prev: Mapping<u64, u64>,
+    head: Var<u64>,
+    tail: Var<u64>,
+    len: Var<u64>,
+}
+
+#[odra::module]
+impl IdList {
+    pub fn len(&self) -> u64 {
+        self.len.get_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn head(&self) -> Option<u64> {
+        Some(self.head.get_or_default()).filter(|id| *id != 0)
+    }
+
+    pub fn next(&self, id: u64) -> Option<u64> {
+        self.next.get(&id).filter(|id| *id != 0)
+    }
+
+    pub fn push_back(&mut self, id: u64) {
+        let tail = self.tail.get_or_default();
+        if tail == 0 {
+            self.head.set(id);
+        } else {
+            self.next.set(&tail, id);
+        }
+        self.prev.set(&id, tail);
+        self.next.set(&id, 0);
+        self.tail.set(id);
+        self.len.set(self.len() + 1);
+    }
+
+    /// Unlinks `id`, which must be in this list
+    pub fn remove(&mut self, id: u64) {
+        let prev = self.prev.get_or_default(&id);
+        let next = self.next.get_or_default(&id);
+
+        if prev == 0 {
+            self.head.set(next);
+        } else {
+            self.next.set(&prev, next);
+        }
+        if next == 0 {
+            self.tail.set(prev);
+        } else {
+            self.prev.set(&next, prev);
+        }
+
+        self.next.set(&id, 0);
+        self.prev.set(&id, 0);
+        self.len.set(self.len().saturating_sub(1));
+    }
+
+    /// Up to `limit` ids after skipping `offset`, walking from the head
+    pub fn page(&self, offset: u64, limit: u64) -> Vec<u64> {
+        let mut ids = Vec::new();
+        let mut cursor = self.head();
+        let mut index = 0u64;
+
+        while let Some(id) = cursor {
+            if ids.len() as u64 >= limit {
+                break;
+            }
+            if index >= offset {
+                ids.push(id);
+            }
+            index += 1;
+            cursor = self.next(id);
+        }
+
+        ids
+    }
+}
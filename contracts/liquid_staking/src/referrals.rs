@@ -0,0 +1,174 @@
+use odra::prelude::*;
+use odra::casper_types::U512;
+
+use crate::liquid_staking::{Error, BASIS_POINTS};
+
+// Fixed-point scale for rewards per unit of referred volume
+const REWARD_SCALE: u128 = 1_000_000_000_000_000_000;
+
+/// Referred volume and the share of protocol fees it earns
+/// A referrer's volume is the referred stake that is still active: a user is
+/// bound to their first referrer, and unstaking takes referred stake off
+/// first. Each harvest raises a global reward-per-volume index; a referrer's
+/// due is their volume times the index growth since they last settled, so
+/// paying out never needs to walk every referrer.
+#[odra::module]
+pub struct ReferralBook {
+    fee_share_bps: Var<u64>,
+
+    volume: Mapping<Address, U512>,
+    total_volume: Var<U512>,
+    referrer_of: Mapping<Address, Address>,
+    referred_stake: Mapping<Address, U512>,
+    // Referred stake each withdrawal request took off, restored if it is cancelled
+    withdrawn: Mapping<u64, U512>,
+
+    reward_per_volume: Var<U512>,
+    // Index value each referrer was last settled at
+    settled_index: Mapping<Address, U512>,
+    accrued: Mapping<Address, U512>,
+    total_distributed: Var<U512>,
+    total_claimed: Var<U512>,
+}
+
+#[odra::module]
+impl ReferralBook {
+    pub fn init(&mut self) {
+        self.fee_share_bps.set(0);
+        self.total_volume.set(U512::zero());
+        self.reward_per_volume.set(U512::zero());
+        self.total_distributed.set(U512::zero());
+        self.total_claimed.set(U512::zero());
+    }
+
+    pub fn fee_share_bps(&self) -> u64 {
+        self.fee_share_bps.get_or_default()
+    }
+
+    pub fn set_fee_share_bps(&mut self, share_bps: u64) {
+        if share_bps > BASIS_POINTS {
+            self.env().revert(Error::InvalidConfig);
+        }
+        self.fee_share_bps.set(share_bps);
+    }
+
+    pub fn volume(&self, referrer: Address) -> U512 {
+        self.volume.get_or_default(&referrer)
+    }
+
+    pub fn total_volume(&self) -> U512 {
+        self.total_volume.get_or_default()
+    }
+
+    pub fn total_distributed(&self) -> U512 {
+        self.total_distributed.get_or_default()
+    }
+
+    /// Distributed rewards referrers have not claimed yet
+    pub fn outstanding(&self) -> U512 {
+        self.total_distributed().saturating_sub(self.total_claimed.get_or_default())
+    }
+
+    pub fn referrer_of(&self, user: Address) -> Option<Address> {
+        self.referrer_of.get(&user)
+    }
+
+    /// Credits `amount` staked by `user` to their referrer, binding them to
+    /// `referrer` if they have none yet
+    pub fn record(&mut self, user: Address, referrer: Address, amount: U512) {
+        let referrer = self.referrer_of(user).unwrap_or(referrer);
+        self.referrer_of.set(&user, referrer);
+        self.referred_stake.add(&user, amount);
+        self.add_volume(referrer, amount);
+    }
+
+    /// Takes up to `amount` of the user's referred stake out of their
+    /// referrer's volume; returns how much came off
+    pub fn release(&mut self, user: Address, amount: U512) -> U512 {
+        let referrer = match self.referrer_of(user) {
+            Some(referrer) => referrer,
+            None => return U512::zero(),
+        };
+        let referred = self.referred_stake.get_or_default(&user);
+        let released = referred.min(amount);
+        if released.is_zero() {
+            return released;
+        }
+
+        self.referred_stake.set(&user, referred.saturating_sub(released));
+        self.settle(referrer);
+        self.volume.set(&referrer, self.volume(referrer).saturating_sub(released));
+        self.total_volume.set(self.total_volume().saturating_sub(released));
+        released
+    }
+
+    pub fn set_withdrawn(&mut self, request_id: u64, amount: U512) {
+        self.withdrawn.set(&request_id, amount);
+    }
+
+    /// Puts back what a cancelled withdrawal request took off
+    pub fn restore(&mut self, user: Address, request_id: u64) {
+        let amount = self.withdrawn.get_or_default(&request_id);
+        let referrer = match self.referrer_of(user) {
+            Some(referrer) if !amount.is_zero() => referrer,
+            _ => return,
+        };
+        self.withdrawn.set(&request_id, U512::zero());
+        self.referred_stake.add(&user, amount);
+        self.add_volume(referrer, amount);
+    }
+
+    /// Splits the referrers' cut of `protocol_fee` by volume and returns it
+    /// Nothing is taken while there is no referred volume.
+    pub fn distribute(&mut self, protocol_fee: U512) -> U512 {
+        let total_volume = self.total_volume();
+        if total_volume.is_zero() {
+            return U512::zero();
+        }
+
+        let share = protocol_fee.saturating_mul(U512::from(self.fee_share_bps())) / U512::from(BASIS_POINTS);
+        if share.is_zero() {
+            return U512::zero();
+        }
+
+        let index = self.reward_per_volume.get_or_default()
+            .saturating_add(share.saturating_mul(U512::from(REWARD_SCALE)) / total_volume);
+        self.reward_per_volume.set(index);
+        self.total_distributed.add(share);
+
+        share
+    }
+
+    /// Rewards the referrer can claim right now
+    pub fn claimable(&self, referrer: Address) -> U512 {
+        self.accrued.get_or_default(&referrer).saturating_add(self.unsettled(referrer))
+    }
+
+    pub fn take(&mut self, referrer: Address) -> U512 {
+        self.settle(referrer);
+        let amount = self.accrued.get_or_default(&referrer);
+        self.accrued.set(&referrer, U512::zero());
+        self.total_claimed.add(amount);
+        amount
+    }
+
+    fn unsettled(&self, referrer: Address) -> U512 {
+        let growth = self.reward_per_volume.get_or_default()
+            .saturating_sub(self.settled_index.get_or_default(&referrer));
+        self.volume(referrer).saturating_mul(growth) / U512::from(REWARD_SCALE)
+    }
+
+    fn add_volume(&mut self, referrer: Address, amount: U512) {
+        self.settle(referrer);
+        self.volume.add(&referrer, amount);
+        self.total_volume.add(amount);
+    }
+
+    fn settle(&mut self, referrer: Address) {
+        let due = self.unsettled(referrer);
+        if !due.is_zero() {
+            self.accrued.add(&referrer, due);
+        }
+        self.settled_index.set(&referrer, self.reward_per_volume.get_or_default());
+    }
+}
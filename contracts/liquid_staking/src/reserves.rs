@@ -0,0 +1,61 @@
+use odra::prelude::*;
+use odra::casper_types::U512;
+
+/// Where the pool's CSPR sits outside the contract balance
+/// Delegating: withdrawn by the keeper, delegation not yet confirmed.
+/// Delegated: confirmed on validators, including compounded rewards.
+/// Undelegating: undelegation confirmed, CSPR not yet deposited back.
+#[odra::module]
+pub struct ReserveLedger {
+    delegating: Var<U512>,
+    delegated: Var<U512>,
+    undelegating: Var<U512>,
+}
+
+#[odra::module]
+impl ReserveLedger {
+    pub fn init(&mut self) {
+        self.delegating.set(U512::zero());
+        self.delegated.set(U512::zero());
+        self.undelegating.set(U512::zero());
+    }
+
+    pub fn delegating(&self) -> U512 {
+        self.delegating.get_or_default()
+    }
+
+    pub fn delegated(&self) -> U512 {
+        self.delegated.get_or_default()
+    }
+
+    pub fn undelegating(&self) -> U512 {
+        self.undelegating.get_or_default()
+    }
+
+    /// Keeper took liquid CSPR to delegate
+    pub fn start_delegation(&mut self, amount: U512) {
+        self.delegating.add(amount);
+    }
+
+    /// Delegation landed on a validator
+    pub fn confirm_delegation(&mut self, amount: U512) {
+        self.delegating.set(self.delegating().saturating_sub(amount));
+        self.delegated.add(amount);
+    }
+
+    /// Undelegation started, CSPR is unbonding
+    pub fn confirm_undelegation(&mut self, amount: U512) {
+        self.delegated.set(self.delegated().saturating_sub(amount));
+        self.undelegating.add(amount);
+    }
+
+    /// Unbonded CSPR came back to the contract
+    pub fn complete_undelegation(&mut self, amount: U512) {
+        self.undelegating.set(self.undelegating().saturating_sub(amount));
+    }
+
+    /// Rewards compound on the validators
+    pub fn add_rewards(&mut self, amount: U512) {
+        self.delegated.add(amount);
+    }
+}
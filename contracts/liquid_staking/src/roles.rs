@@ -0,0 +1,93 @@
+use odra::prelude::*;
+
+use crate::liquid_staking::{Error, BASIS_POINTS};
+
+/// Owner and keepers of LiquidStaking, and whether the keepers are still alive
+/// Anyone bonded in the KeeperBonds contract may also submit reports, but only
+/// the configured keeper's calls count as heartbeats. Once the keeper has been
+/// silent for `max_missed_eras`, anyone may put the pool in degraded mode.
+#[odra::module]
+pub struct Roles {
+    owner: Var<Address>,
+    keeper: Var<Address>,
+    // Keepers bonded here may submit reports, nothing else
+    keeper_bonds: Var<Address>,
+
+    // Block time of the keeper's last call, in milliseconds
+    last_heartbeat: Var<u64>,
+    max_missed_eras: Var<u64>,
+    degraded: Var<bool>,
+    // Paid to permissionless callers out of the amount they process
+    bounty_bps: Var<u64>,
+}
+
+#[odra::module]
+impl Roles {
+    pub fn init(&mut self, owner: Address, keeper: Address, max_missed_eras: u64, bounty_bps: u64) {
+        self.owner.set(owner);
+        self.keeper.set(keeper);
+        self.last_heartbeat.set(self.env().get_block_time());
+        self.max_missed_eras.set(max_missed_eras);
+        self.degraded.set(false);
+        self.bounty_bps.set(bounty_bps);
+    }
+
+    pub fn owner(&self) -> Address {
+        self.owner.get_or_revert_with(Error::NotInitialized)
+    }
+
+    pub fn keeper(&self) -> Address {
+        self.keeper.get_or_revert_with(Error::NotInitialized)
+    }
+
+    pub fn set_keeper(&mut self, keeper: Address) {
+        self.keeper.set(keeper);
+        self.heartbeat();
+    }
+
+    pub fn keeper_bonds(&self) -> Option<Address> {
+        self.keeper_bonds.get()
+    }
+
+    pub fn set_keeper_bonds(&mut self, keeper_bonds: Address) {
+        self.keeper_bonds.set(keeper_bonds);
+    }
+
+    pub fn heartbeat(&mut self) {
+        self.last_heartbeat.set(self.env().get_block_time());
+    }
+
+    pub fn last_heartbeat(&self) -> u64 {
+        self.last_heartbeat.get_or_default()
+    }
+
+    pub fn max_missed_eras(&self) -> u64 {
+        self.max_missed_eras.get_or_default()
+    }
+
+    pub fn bounty_bps(&self) -> u64 {
+        self.bounty_bps.get_or_default()
+    }
+
+    pub fn set_liveness(&mut self, max_missed_eras: u64, bounty_bps: u64) {
+        if max_missed_eras == 0 || bounty_bps > BASIS_POINTS {
+            self.env().revert(Error::InvalidConfig);
+        }
+        self.max_missed_eras.set(max_missed_eras);
+        self.bounty_bps.set(bounty_bps);
+    }
+
+    /// Whether the keeper has missed more eras than allowed
+    pub fn keeper_overdue(&self, era_duration_ms: u64) -> bool {
+        let silence = self.env().get_block_time().saturating_sub(self.last_heartbeat());
+        silence >= self.max_missed_eras().saturating_mul(era_duration_ms)
+    }
+
+    pub fn degraded(&self) -> bool {
+        self.degraded.get_or_default()
+    }
+
+    pub fn set_degraded(&mut self, degraded: bool) {
+        self.degraded.set(degraded);
+    }
+}
@@ -0,0 +1,246 @@
+use odra::prelude::*;
+use odra::casper_types::{PublicKey, U512};
+
+use crate::liquid_staking::{Error, UndelegationBatch, WithdrawalRequest, WithdrawalStatus};
+use crate::queue::{IdList, IdQueue};
+
+/// Withdrawal requests and the undelegation batches that pay for them
+/// Keeper confirmations cover a validator's requests in the order they were
+/// made; a request stays cancellable until some of it is covered. Deposits
+/// are matched against the oldest open batch, then fund requests in order.
+#[odra::module]
+pub struct WithdrawalBook {
+    requests: Mapping<u64, WithdrawalRequest>,
+    // Open (unclaimed) requests per user, in creation order
+    user_requests: Mapping<Address, IdList>,
+    // Every request a user has made, claimed or not
+    user_history: Mapping<Address, IdQueue>,
+    next_request_id: Var<u64>,
+
+    batches: Mapping<u64, UndelegationBatch>,
+    next_batch_id: Var<u64>,
+    open_batches: Mapping<PublicKey, IdQueue>,
+
+    // Requests whose undelegation the keeper has not fully confirmed
+    unconfirmed_requests: Mapping<PublicKey, IdQueue>,
+    // Confirmed CSPR already applied to the head of unconfirmed_requests
+    confirm_credit: Mapping<PublicKey, U512>,
+
+    unfunded_requests: Mapping<PublicKey, IdQueue>,
+    // Deposited CSPR not yet enough to fund the next request
+    funding_credit: Mapping<PublicKey, U512>,
+    funded_unclaimed: Var<U512>,
+    // Requests made and not yet claimed or cancelled
+    total_pending: Var<U512>,
+}
+
+#[odra::module]
+impl WithdrawalBook {
+    pub fn init(&mut self) {
+        self.next_request_id.set(1);
+        self.next_batch_id.set(1);
+        self.funded_unclaimed.set(U512::zero());
+        self.total_pending.set(U512::zero());
+    }
+
+    pub fn request(&self, request_id: u64) -> Option<WithdrawalRequest> {
+        self.requests.get(&request_id)
+    }
+
+    pub fn set_request(&mut self, request_id: u64, request: WithdrawalRequest) {
+        self.requests.set(&request_id, request);
+    }
+
+    pub fn first_user_request(&self, user: Address) -> Option<u64> {
+        self.user_requests.module(&user).head()
+    }
+
+    pub fn next_user_request(&self, user: Address, request_id: u64) -> Option<u64> {
+        self.user_requests.module(&user).next(request_id)
+    }
+
+    pub fn remove_user_request(&mut self, user: Address, request_id: u64) {
+        self.user_requests.module(&user).remove(request_id);
+    }
+
+    pub fn user_request_count(&self, user: Address) -> u64 {
+        self.user_requests.module(&user).len()
+    }
+
+    pub fn user_request_page(&self, user: Address, offset: u64, limit: u64) -> Vec<u64> {
+        self.user_requests.module(&user).page(offset, limit)
+    }
+
+    pub fn user_history_count(&self, user: Address) -> u64 {
+        self.user_history.module(&user).len()
+    }
+
+    /// Up to `limit` of the user's request ids, oldest first
+    pub fn user_history_page(&self, user: Address, offset: u64, limit: u64) -> Vec<u64> {
+        let history = self.user_history.module(&user);
+        let end = offset.saturating_add(limit).min(history.len());
+        (offset..end).filter_map(|i| history.get(i)).collect()
+    }
+
+    pub fn create_request(
+        &mut self,
+        user: Address,
+        validator: PublicKey,
+        amount: U512,
+        unlock_era: u64,
+    ) -> u64 {
+        let request_id = self.next_request_id.get_or_default();
+        self.next_request_id.set(request_id + 1);
+
+        self.requests.set(&request_id, WithdrawalRequest {
+            user,
+            validator: validator.clone(),
+            amount,
+            unlock_era,
+            status: WithdrawalStatus::Pending,
+        });
+
+        self.total_pending.add(amount);
+        self.user_requests.module(&user).push_back(request_id);
+        self.user_history.module(&user).push(request_id);
+
+        self.unconfirmed_requests.module(&validator).push(request_id);
+        self.unfunded_requests.module(&validator).push(request_id);
+
+        request_id
+    }
+
+    /// Applies a keeper confirmation to `validator`'s requests, oldest first
+    pub fn confirm(&mut self, validator: PublicKey, amount: U512) {
+        let mut credit = self.confirm_credit.get_or_default(&validator).saturating_add(amount);
+        let mut unconfirmed = self.unconfirmed_requests.module(&validator);
+
+        while let Some(request_id) = unconfirmed.peek() {
+            let request = self.requests.get(&request_id)
+                .unwrap_or_revert_with(&self.env(), Error::RequestNotFound);
+
+            if request.status == WithdrawalStatus::Cancelled {
+                unconfirmed.pop();
+                continue;
+            }
+            if credit < request.amount {
+                break;
+            }
+            credit = credit.saturating_sub(request.amount);
+            unconfirmed.pop();
+        }
+
+        self.confirm_credit.set(&validator, credit);
+    }
+
+    /// Whether none of the request's undelegation has been confirmed yet
+    pub fn is_unconfirmed(&self, request_id: u64, validator: &PublicKey) -> bool {
+        // Queues hold ids in ascending order, so anything behind the head is untouched
+        match self.unconfirmed_requests.module(validator).peek() {
+            Some(head) if request_id > head => true,
+            Some(head) if request_id == head => self.confirm_credit.get_or_default(validator).is_zero(),
+            _ => false,
+        }
+    }
+
+    pub fn batch(&self, batch_id: u64) -> Option<UndelegationBatch> {
+        self.batches.get(&batch_id)
+    }
+
+    pub fn record_batch(&mut self, validator: PublicKey, amount: U512, unlock_era: u64) -> u64 {
+        let batch_id = self.next_batch_id.get_or_default();
+        self.next_batch_id.set(batch_id + 1);
+
+        self.batches.set(&batch_id, UndelegationBatch {
+            validator: validator.clone(),
+            amount,
+            deposited: U512::zero(),
+            unlock_era,
+        });
+        self.open_batches.module(&validator).push(batch_id);
+
+        batch_id
+    }
+
+    /// Undelegated CSPR for `validator` not yet deposited back
+    pub fn open_batch_ids(&self, validator: PublicKey) -> Vec<u64> {
+        let queue = self.open_batches.module(&validator);
+        (0..queue.len()).filter_map(|i| queue.get(i)).collect()
+    }
+
+    /// Settles a keeper deposit and returns the requests it funded
+    pub fn fund(&mut self, validator: PublicKey, amount: U512) -> Vec<u64> {
+        let mut remaining = amount;
+        let mut batches = self.open_batches.module(&validator);
+
+        while !remaining.is_zero() {
+            let batch_id = match batches.peek() {
+                Some(id) => id,
+                None => self.env().revert(Error::UnexpectedDeposit),
+            };
+            let mut batch = self.batches.get(&batch_id)
+                .unwrap_or_revert_with(&self.env(), Error::BatchNotFound);
+
+            let outstanding = batch.amount.saturating_sub(batch.deposited);
+            let matched = remaining.min(outstanding);
+            batch.deposited = batch.deposited.saturating_add(matched);
+            remaining = remaining.saturating_sub(matched);
+
+            if batch.deposited >= batch.amount {
+                batches.pop();
+            }
+            self.batches.set(&batch_id, batch);
+        }
+
+        self.fund_requests(validator, amount)
+    }
+
+    /// Funds `validator`'s requests in order from CSPR already in the contract
+    pub fn fund_requests(&mut self, validator: PublicKey, amount: U512) -> Vec<u64> {
+        let mut credit = self.funding_credit.get_or_default(&validator).saturating_add(amount);
+        let mut unfunded = self.unfunded_requests.module(&validator);
+        let mut funded = Vec::new();
+
+        while let Some(request_id) = unfunded.peek() {
+            let mut request = self.requests.get(&request_id)
+                .unwrap_or_revert_with(&self.env(), Error::RequestNotFound);
+
+            if request.status == WithdrawalStatus::Pending {
+                if credit < request.amount {
+                    break;
+                }
+                credit = credit.saturating_sub(request.amount);
+                self.funded_unclaimed.add(request.amount);
+                request.status = WithdrawalStatus::Funded;
+                self.requests.set(&request_id, request);
+                funded.push(request_id);
+            }
+            unfunded.pop();
+        }
+
+        self.funding_credit.set(&validator, credit);
+        funded
+    }
+
+    /// Marks a funded request as paid out
+    pub fn settle_claim(&mut self, amount: U512) {
+        let funded = self.funded_unclaimed.get_or_default();
+        self.funded_unclaimed.set(funded.saturating_sub(amount));
+        self.release_pending(amount);
+    }
+
+    pub fn total_pending(&self) -> U512 {
+        self.total_pending.get_or_default()
+    }
+
+    /// Drops a request's amount from the outstanding total
+    pub fn release_pending(&mut self, amount: U512) {
+        let pending = self.total_pending.get_or_default();
+        self.total_pending.set(pending.saturating_sub(amount));
+    }
+
+    /// Funded requests that have not been claimed yet
+    pub fn funded_unclaimed(&self) -> U512 {
+        self.funded_unclaimed.get_or_default()
+    }
+}
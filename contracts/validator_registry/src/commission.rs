@@ -0,0 +1,127 @@
+use odra::casper_types::PublicKey;
+use odra::prelude::*;
+
+#[odra::odra_type]
+pub struct CommissionConfig {
+    /// Largest fee rise, in percentage points, tolerated within the lookback
+    pub max_increase: u64,
+    pub lookback_eras: u64,
+    /// Eras a flagged validator stays excluded after the spike
+    pub cool_off_eras: u64,
+}
+
+impl Default for CommissionConfig {
+    fn default() -> Self {
+        Self {
+            max_increase: 5,
+            lookback_eras: 84,
+            cool_off_eras: 84,
+        }
+    }
+}
+
+#[odra::odra_type]
+pub struct FeeChange {
+    pub era: u64,
+    pub fee: u64,
+}
+
+/// A validator's fee each time it changed, oldest first
+#[odra::module]
+pub struct FeeLog {
+    changes: Mapping<u32, FeeChange>,
+    len: Var<u32>,
+}
+
+#[odra::module]
+impl FeeLog {
+    pub fn len(&self) -> u32 {
+        self.len.get_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: u32) -> Option<FeeChange> {
+        self.changes.get(&index)
+    }
+
+    /// Appends `fee` unless it is already the latest one; true if appended
+    pub fn push(&mut self, era: u64, fee: u64) -> bool {
+        let len = self.len();
+        if len.checked_sub(1).and_then(|i| self.get(i)).is_some_and(|last| last.fee == fee) {
+            return false;
+        }
+        self.changes.set(&len, FeeChange { era, fee });
+        self.len.set(len + 1);
+        true
+    }
+
+    /// Lowest fee in effect at any point since `since`
+    pub fn lowest_since(&self, since: u64) -> Option<u64> {
+        let mut lowest: Option<u64> = None;
+        for index in (0..self.len()).rev() {
+            let change = self.get(index)?;
+            lowest = Some(lowest.map_or(change.fee, |fee| fee.min(change.fee)));
+            // This one was already in effect when the window opened
+            if change.era <= since {
+                break;
+            }
+        }
+        lowest
+    }
+}
+
+/// Fee history and the validators excluded for raising fees too fast
+#[odra::module]
+pub struct CommissionMonitor {
+    fees: Mapping<PublicKey, FeeLog>,
+    flagged_until: Mapping<PublicKey, u64>,
+    config: Var<CommissionConfig>,
+}
+
+#[odra::module]
+impl CommissionMonitor {
+    pub fn config(&self) -> CommissionConfig {
+        self.config.get_or_default()
+    }
+
+    pub fn set_config(&mut self, config: CommissionConfig) {
+        self.config.set(config);
+    }
+
+    /// Logs the fee reported at `era`
+    /// Returns the lowest fee within the lookback when the change is a spike
+    /// from it, flagging the validator until the cool-off ends. Holding a
+    /// raised fee is not a new spike.
+    pub fn record(&mut self, pubkey: &PublicKey, era: u64, fee: u64) -> Option<u64> {
+        let config = self.config();
+        let mut log = self.fees.module(pubkey);
+        let baseline = log.lowest_since(era.saturating_sub(config.lookback_eras));
+        if !log.push(era, fee) {
+            return None;
+        }
+
+        let baseline = baseline.filter(|baseline| fee.saturating_sub(*baseline) > config.max_increase)?;
+        self.flagged_until.set(pubkey, era + config.cool_off_eras);
+        Some(baseline)
+    }
+
+    /// Era from which the validator is eligible again, if it was ever flagged
+    pub fn flagged_until(&self, pubkey: &PublicKey) -> Option<u64> {
+        self.flagged_until.get(pubkey)
+    }
+
+    pub fn is_flagged(&self, pubkey: &PublicKey, era: u64) -> bool {
+        self.flagged_until(pubkey).is_some_and(|until| era < until)
+    }
+
+    pub fn fee_change(&self, pubkey: &PublicKey, index: u32) -> Option<FeeChange> {
+        self.fees.module(pubkey).get(index)
+    }
+
+    pub fn fee_change_count(&self, pubkey: &PublicKey) -> u32 {
+        self.fees.module(pubkey).len()
+    }
+}
@@ -0,0 +1,87 @@
+use odra::casper_types::PublicKey;
+use odra::prelude::*;
+
+use crate::validator_registry::ValidatorData;
+
+/// Ascending list of eras with a floor lookup
+/// Lookups binary search over single slots, so they stay cheap however long
+/// the history grows.
+#[odra::module]
+pub struct EraIndex {
+    eras: Mapping<u32, u64>,
+    len: Var<u32>,
+}
+
+#[odra::module]
+impl EraIndex {
+    pub fn len(&self) -> u32 {
+        self.len.get_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn last(&self) -> Option<u64> {
+        self.len().checked_sub(1).and_then(|i| self.eras.get(&i))
+    }
+
+    /// Appends `era` unless it is already the latest entry
+    pub fn push(&mut self, era: u64) {
+        if self.last() == Some(era) {
+            return;
+        }
+        let len = self.len();
+        self.eras.set(&len, era);
+        self.len.set(len + 1);
+    }
+
+    /// Latest recorded era at or before `era`
+    pub fn floor(&self, era: u64) -> Option<u64> {
+        let (mut low, mut high) = (0u32, self.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if self.eras.get_or_default(&mid) <= era {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        low.checked_sub(1).and_then(|i| self.eras.get(&i))
+    }
+}
+
+/// Validator data and network p_avg as they stood after each era's update
+#[odra::module]
+pub struct ScoreHistory {
+    snapshots: Mapping<(PublicKey, u64), ValidatorData>,
+    validator_eras: Mapping<PublicKey, EraIndex>,
+
+    p_avg: Mapping<u64, u64>,
+    p_avg_eras: SubModule<EraIndex>,
+}
+
+#[odra::module]
+impl ScoreHistory {
+    pub fn record_validator(&mut self, pubkey: &PublicKey, era: u64, data: ValidatorData) {
+        self.snapshots.set(&(pubkey.clone(), era), data);
+        self.validator_eras.module(pubkey).push(era);
+    }
+
+    /// The validator's data in effect at `era`
+    pub fn validator_at(&self, pubkey: &PublicKey, era: u64) -> Option<ValidatorData> {
+        let updated = self.validator_eras.module(pubkey).floor(era)?;
+        self.snapshots.get(&(pubkey.clone(), updated))
+    }
+
+    /// Later batches in the same era overwrite the era's value
+    pub fn record_p_avg(&mut self, era: u64, p_avg: u64) {
+        self.p_avg.set(&era, p_avg);
+        self.p_avg_eras.push(era);
+    }
+
+    pub fn p_avg_at(&self, era: u64) -> Option<u64> {
+        let updated = self.p_avg_eras.floor(era)?;
+        self.p_avg.get(&updated)
+    }
+}
@@ -3,5 +3,12 @@
 
 extern crate alloc;
 
+pub mod commission;
+pub mod history;
+pub mod lifecycle;
+pub mod merkle;
+pub mod network_average;
+pub mod scoring;
 pub mod validator_registry;
+pub mod validator_set;
 pub use validator_registry::*;
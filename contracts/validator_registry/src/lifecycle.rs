@@ -0,0 +1,68 @@
+#[odra::odra_type]
+#[derive(Copy)]
+pub enum ValidatorState {
+    /// Newly seen, not yet eligible for stake
+    Candidate,
+    Active,
+    /// Temporary outage; keeps its entry but receives no new stake
+    Probation,
+    Jailed,
+    /// Permanent exit; prunable once its dwell has passed
+    Retired,
+}
+
+/// Minimum eras spent in a state before moving up out of it
+#[odra::odra_type]
+pub struct LifecycleConfig {
+    pub candidate_eras: u64,
+    pub probation_eras: u64,
+    pub jail_eras: u64,
+    /// Eras before a retired entry can be pruned
+    pub retired_eras: u64,
+}
+
+impl Default for LifecycleConfig {
+    fn default() -> Self {
+        Self {
+            candidate_eras: 2,
+            probation_eras: 2,
+            jail_eras: 12,
+            retired_eras: 6,
+        }
+    }
+}
+
+impl LifecycleConfig {
+    /// Eras `from` must be held before moving to `to`
+    /// Moves toward safety (probation, jail, retirement) are immediate.
+    pub fn dwell(&self, from: ValidatorState, to: ValidatorState) -> u64 {
+        use ValidatorState::*;
+        match (from, to) {
+            (Candidate, Active) => self.candidate_eras,
+            (Probation, Active) => self.probation_eras,
+            (Jailed, Probation) => self.jail_eras,
+            _ => 0,
+        }
+    }
+}
+
+impl ValidatorState {
+    pub fn can_move_to(self, to: ValidatorState) -> bool {
+        use ValidatorState::*;
+        matches!(
+            (self, to),
+            (Candidate, Active)
+                | (Active, Probation)
+                | (Probation, Active)
+                | (Candidate | Active | Probation, Jailed)
+                | (Jailed, Probation)
+                | (Candidate | Active | Probation | Jailed, Retired)
+        )
+    }
+
+    /// Moves the keeper may make; jailing and retirement are owner-only
+    pub fn keeper_may_move_to(self, to: ValidatorState) -> bool {
+        use ValidatorState::*;
+        matches!((self, to), (Candidate, Active) | (Active, Probation) | (Probation, Active))
+    }
+}
@@ -0,0 +1,33 @@
+use odra::casper_types::bytesrepr::ToBytes;
+use odra::prelude::*;
+
+use crate::validator_registry::ValidatorUpdateData;
+
+pub type Hash = [u8; 32];
+
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+/// Bytes hashed into a leaf: `0x00 || era || data`, all in Casper encoding
+/// The prefixes keep a leaf from ever passing for an inner node.
+pub fn leaf_preimage(era: u64, data: &ValidatorUpdateData) -> Vec<u8> {
+    let mut bytes = vec![LEAF_PREFIX];
+    bytes.extend(era.to_le_bytes());
+    bytes.extend(data.to_bytes().unwrap_or_default());
+    bytes
+}
+
+/// Bytes hashed into a parent: `0x01 || lower || higher`
+/// Ordering the pair lets proofs skip left/right flags.
+pub fn node_preimage(a: &Hash, b: &Hash) -> Vec<u8> {
+    let (low, high) = if a <= b { (a, b) } else { (b, a) };
+    let mut bytes = vec![NODE_PREFIX];
+    bytes.extend_from_slice(low);
+    bytes.extend_from_slice(high);
+    bytes
+}
+
+/// Folds `proof` into `leaf` with `hash`, giving the root it implies
+pub fn root_from_proof(leaf: Hash, proof: &[Hash], hash: impl Fn(&[u8]) -> Hash) -> Hash {
+    proof.iter().fold(leaf, |node, sibling| hash(&node_preimage(&node, sibling)))
+}
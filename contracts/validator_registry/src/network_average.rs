@@ -0,0 +1,110 @@
+use odra::casper_types::U512;
+use odra::prelude::*;
+
+use crate::lifecycle::ValidatorState;
+use crate::validator_registry::ValidatorData;
+
+#[odra::odra_type]
+#[derive(Copy, Default)]
+pub enum PAvgMode {
+    /// Every scoring validator counts the same
+    #[default]
+    Count,
+    /// Validators count in proportion to their delegated stake
+    Stake,
+}
+
+/// Running sums behind the network p_avg
+/// Each write swaps a validator's old contribution for its new one, so the
+/// average follows along as batches land instead of being recomputed.
+#[odra::module]
+pub struct NetworkAverage {
+    score_sum: Var<u64>,
+    count: Var<u64>,
+    weighted_score_sum: Var<U512>,
+    stake_sum: Var<U512>,
+}
+
+#[odra::module]
+impl NetworkAverage {
+    pub fn replace(&mut self, old: Option<ValidatorData>, new: Option<ValidatorData>) {
+        if let Some(old) = old.filter(counts) {
+            self.score_sum.subtract(old.p_score);
+            self.count.subtract(1);
+            self.weighted_score_sum.subtract(old.delegated_stake * old.p_score);
+            self.stake_sum.subtract(old.delegated_stake);
+        }
+        if let Some(new) = new.filter(counts) {
+            self.score_sum.add(new.p_score);
+            self.count.add(1);
+            self.weighted_score_sum.add(new.delegated_stake * new.p_score);
+            self.stake_sum.add(new.delegated_stake);
+        }
+    }
+
+    /// Counted network stake once every `(old, stake)` swap in a batch has landed
+    pub fn stake_after(&self, swaps: Vec<(Option<ValidatorData>, U512)>) -> U512 {
+        swaps.into_iter().fold(self.stake_sum.get_or_default(), |total, (old, stake)| {
+            total - old.filter(counts).map(|data| data.delegated_stake).unwrap_or_default() + stake
+        })
+    }
+
+    pub fn totals(&self) -> NetworkTotals {
+        NetworkTotals {
+            score_sum: self.score_sum.get_or_default(),
+            count: self.count.get_or_default(),
+            weighted_score_sum: self.weighted_score_sum.get_or_default(),
+            stake_sum: self.stake_sum.get_or_default(),
+        }
+    }
+
+    pub fn p_avg(&self, mode: PAvgMode) -> Option<u64> {
+        self.totals().p_avg(mode)
+    }
+}
+
+/// The sums a p_avg is taken from
+#[odra::odra_type]
+#[derive(Default)]
+pub struct NetworkTotals {
+    pub score_sum: u64,
+    /// Scoring validators that are not jailed or retired
+    pub count: u64,
+    pub weighted_score_sum: U512,
+    pub stake_sum: U512,
+}
+
+impl NetworkTotals {
+    /// Folds in a validator's contribution, if it is scoring
+    pub fn add(&mut self, data: &ValidatorData) {
+        if counts(data) {
+            self.score_sum += data.p_score;
+            self.count += 1;
+            self.weighted_score_sum += data.delegated_stake * data.p_score;
+            self.stake_sum += data.delegated_stake;
+        }
+    }
+
+    /// None while nothing is scoring, or no stake is reported in stake mode
+    pub fn p_avg(&self, mode: PAvgMode) -> Option<u64> {
+        match mode {
+            PAvgMode::Count => (self.count > 0).then(|| self.score_sum / self.count),
+            PAvgMode::Stake => {
+                (!self.stake_sum.is_zero()).then(|| (self.weighted_score_sum / self.stake_sum).as_u64())
+            }
+        }
+    }
+}
+
+/// Share of `total` network stake held by `stake`
+pub fn stake_share_bps(stake: U512, total: U512) -> u64 {
+    if total.is_zero() {
+        return 0;
+    }
+    (stake * 10_000u64 / total).as_u64()
+}
+
+/// Scoring validators that are not jailed or retired
+fn counts(data: &ValidatorData) -> bool {
+    data.p_score > 0 && !matches!(data.state, ValidatorState::Jailed | ValidatorState::Retired)
+}
@@ -0,0 +1,121 @@
+use odra::prelude::*;
+
+const BASIS_POINTS: u64 = 10_000;
+
+/// How strongly each factor can pull a score down, in basis points
+/// A score starts at 100 and each factor scales it by
+/// `1 - weight * (1 - component / 100)`, so a full weight applies the
+/// component as a straight multiplier and a zero weight ignores it.
+#[odra::odra_type]
+pub struct ScoreFormula {
+    pub fee_weight: u64,
+    pub decay_weight: u64,
+    pub uptime_weight: u64,
+    pub stake_share_weight: u64,
+    pub stability_weight: u64,
+    /// Network stake share up to which a validator takes no stake penalty;
+    /// above it the stake component falls as `100 * threshold / share`
+    pub stake_share_threshold_bps: u64,
+}
+
+impl ScoreFormula {
+    /// `(100 - fee) * decay_factor / 100`, the original hardcoded formula,
+    /// with a half-weight decentralization penalty above 10% of network stake
+    pub fn standard() -> Self {
+        Self {
+            fee_weight: BASIS_POINTS,
+            decay_weight: BASIS_POINTS,
+            uptime_weight: 0,
+            stake_share_weight: BASIS_POINTS / 2,
+            stability_weight: 0,
+            stake_share_threshold_bps: 1_000,
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        [
+            self.fee_weight,
+            self.decay_weight,
+            self.uptime_weight,
+            self.stake_share_weight,
+            self.stability_weight,
+        ]
+        .iter()
+        .all(|weight| *weight <= BASIS_POINTS)
+            && (1..=BASIS_POINTS).contains(&self.stake_share_threshold_bps)
+    }
+}
+
+/// Everything a formula scores; percentages run 0..=100
+#[odra::odra_type]
+pub struct ScoreInputs {
+    pub fee: u64,
+    pub is_active: bool,
+    pub decay_factor: u64,
+    pub uptime: u64,
+    /// The validator's share of network stake
+    pub stake_share_bps: u64,
+    /// 100 for an unchanged or lowered fee, less the more it rose
+    pub commission_stability: u64,
+}
+
+/// Formula versions, numbered from 1; the latest scores new updates
+#[odra::module]
+pub struct ScoreModel {
+    formulas: Mapping<u32, ScoreFormula>,
+    current: Var<u32>,
+}
+
+#[odra::module]
+impl ScoreModel {
+    pub fn current_version(&self) -> u32 {
+        self.current.get_or_default()
+    }
+
+    pub fn formula(&self, version: u32) -> Option<ScoreFormula> {
+        self.formulas.get(&version)
+    }
+
+    /// Stores `formula` as the next version and makes it current
+    /// Returns None, storing nothing, if a weight exceeds 100% or the stake
+    /// share threshold is outside (0, 100%].
+    pub fn publish(&mut self, formula: ScoreFormula) -> Option<u32> {
+        if !formula.is_valid() {
+            return None;
+        }
+        let version = self.current_version() + 1;
+        self.formulas.set(&version, formula);
+        self.current.set(version);
+        Some(version)
+    }
+
+    pub fn score(&self, version: u32, inputs: ScoreInputs) -> Option<u64> {
+        let formula = self.formula(version)?;
+        if !inputs.is_active {
+            return Some(0);
+        }
+
+        // The decentralization penalty: outsized shares score lower
+        let threshold = formula.stake_share_threshold_bps;
+        let stake_component = if inputs.stake_share_bps <= threshold {
+            100
+        } else {
+            100 * threshold / inputs.stake_share_bps
+        };
+        let factors = [
+            (formula.fee_weight, 100u64.saturating_sub(inputs.fee)),
+            (formula.decay_weight, inputs.decay_factor),
+            (formula.uptime_weight, inputs.uptime),
+            (formula.stake_share_weight, stake_component),
+            (formula.stability_weight, inputs.commission_stability),
+        ];
+
+        // Kept at four extra decimals so a full weight multiplies exactly
+        let scaled = factors.iter().fold(100 * BASIS_POINTS, |score, (weight, component)| {
+            let shortfall = 100u64.saturating_sub((*component).min(100));
+            let factor = BASIS_POINTS - weight * shortfall / 100;
+            score * factor / BASIS_POINTS
+        });
+        Some(scaled / BASIS_POINTS)
+    }
+}
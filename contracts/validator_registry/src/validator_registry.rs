@@ -1,10 +1,27 @@
-use odra::casper_types::{AsymmetricType, PublicKey};
+use odra::casper_types::{PublicKey, U512};
 use odra::prelude::*;
+use odra::ContractRef;
+
+use crate::commission::{CommissionConfig, CommissionMonitor, FeeChange};
+use crate::history::ScoreHistory;
+use crate::lifecycle::{LifecycleConfig, ValidatorState};
+use crate::merkle::{self, Hash};
+use crate::network_average::{self, NetworkAverage, NetworkTotals, PAvgMode};
+use crate::scoring::{ScoreFormula, ScoreInputs, ScoreModel};
+use crate::validator_set::ValidatorSet;
 
 const MIN_P_AVG: u64 = 10;
 const MAX_P_AVG: u64 = 100;
 const MAX_VALIDATORS_PER_UPDATE: u32 = 50;
 const STALE_DATA_ERAS: u64 = 3;
+const ERA_DURATION_MS: u64 = 7_200_000;
+const MAX_PAGE_SIZE: u32 = 100;
+
+#[odra::external_contract]
+pub trait KeeperBondsContract {
+    fn is_bonded(&self, keeper: Address) -> bool;
+    fn record_report(&mut self, reporter: Address, era: u64) -> u64;
+}
 
 #[odra::odra_type]
 pub struct ValidatorData {
@@ -13,6 +30,40 @@ pub struct ValidatorData {
     pub decay_factor: u64,
     pub p_score: u64,
     pub updated_era: u64,
+    pub state: ValidatorState,
+    /// Era the current state was entered
+    pub state_since: u64,
+    pub delegated_stake: U512,
+    /// Score formula version the p_score was computed with
+    pub score_version: u32,
+    pub uptime: u64,
+    pub delegator_count: u32,
+    pub self_bond: U512,
+    pub last_fee_change_era: u64,
+}
+
+/// A committed era: the root over its data, the keeper's sums behind its
+/// p_avg, and what has been proven against it so far
+#[odra::odra_type]
+pub struct ScoreCommit {
+    pub root: Hash,
+    pub count: u32,
+    pub totals: NetworkTotals,
+    pub proven: u32,
+    pub proven_totals: NetworkTotals,
+}
+
+impl ScoreCommit {
+    /// Every leaf has been proven and the proven sums match the keeper's
+    pub fn is_verified(&self) -> bool {
+        self.proven == self.count && self.proven_totals == self.totals
+    }
+}
+
+#[odra::odra_type]
+pub struct ValidatorEntry {
+    pub pubkey: PublicKey,
+    pub data: ValidatorData,
 }
 
 #[odra::odra_type]
@@ -21,15 +72,46 @@ pub struct ValidatorUpdateData {
     pub fee: u64,
     pub is_active: bool,
     pub decay_factor: u64,
+    /// Total stake behind the validator, its self-bond included
+    pub delegated_stake: U512,
+    /// Share of recent eras the validator took part in, 0..=100
+    pub uptime: u64,
+    pub delegator_count: u32,
+    pub self_bond: U512,
+    pub last_fee_change_era: u64,
 }
 
-#[odra::module(events = [ValidatorsUpdated])]
+#[odra::module(events = [
+    ValidatorsUpdated,
+    ValidatorScoreChanged,
+    ValidatorStateChanged,
+    ValidatorPruned,
+    ScoreFormulaPublished,
+    CommissionSpike,
+    ScoresCommitted,
+    ValidatorProven,
+    ScoresVerified,
+    ScoresMismatched
+])]
 pub struct ValidatorRegistry {
     validators: Mapping<PublicKey, ValidatorData>,
     network_p_avg: Var<u64>,
     last_update_era: Var<u64>,
+    // Block time of the last report; unset until the first one
+    last_update_time: Var<u64>,
     keeper: Var<Address>,
     owner: Var<Address>,
+    // Keepers bonded here may submit validator updates
+    keeper_bonds: Var<Address>,
+    history: SubModule<ScoreHistory>,
+    validator_set: SubModule<ValidatorSet>,
+    lifecycle: Var<LifecycleConfig>,
+    network_average: SubModule<NetworkAverage>,
+    p_avg_mode: Var<PAvgMode>,
+    scoring: SubModule<ScoreModel>,
+    commission: SubModule<CommissionMonitor>,
+    // Merkle root and p_avg sums of each committed era's ValidatorUpdateData
+    score_commits: Mapping<u64, ScoreCommit>,
 }
 
 #[odra::module]
@@ -41,15 +123,19 @@ impl ValidatorRegistry {
         self.keeper.set(keeper_address);
         self.network_p_avg.set(80);
         self.last_update_era.set(0);
+        self.lifecycle.set(LifecycleConfig::default());
+        self.scoring.publish(ScoreFormula::standard());
     }
 
+    /// Writes a batch of validator data and folds it into the network p_avg
     pub fn update_validators(
         &mut self,
         validators_data: Vec<ValidatorUpdateData>,
-        p_avg: u64,
         current_era: u64,
     ) {
-        self.require_keeper();
+        let trusted = self.require_reporter();
+        self.record_report(current_era);
+        self.require_era_bound(current_era, trusted);
 
         if validators_data.is_empty() {
             self.env().revert(Error::EmptyBatch);
@@ -65,53 +151,30 @@ impl ValidatorRegistry {
             self.env().revert(Error::TooManyValidators);
         }
 
-        if p_avg < MIN_P_AVG || p_avg > MAX_P_AVG {
-            self.env().revert(Error::InvalidPAvg);
-        }
-
         let mut seen_in_batch = odra::prelude::BTreeSet::new();
 
+        // Stake shares are taken against the whole batch, not whatever
+        // happened to be written before each entry
+        let mut swaps = Vec::with_capacity(validators_data.len());
         for validator_update in validators_data.iter() {
             if !seen_in_batch.insert(validator_update.pubkey.clone()) {
                 self.env().revert(Error::DuplicateValidator);
             }
+            swaps.push((self.stored(&validator_update.pubkey), validator_update.delegated_stake));
+        }
+        let stake_total = self.network_average.stake_after(swaps);
 
-            if validator_update.fee > 100 {
-                self.env().revert(Error::InvalidFee);
-            }
-
-            if validator_update.decay_factor > 100 {
-                self.env().revert(Error::InvalidDecayFactor);
-            }
-
-            if let Some(existing) = self.validators.get(&validator_update.pubkey) {
-                if existing.updated_era == current_era {
-                    self.env().revert(Error::ValidatorAlreadyUpdated);
-                }
-            }
-
-            let p_score = self.calculate_p_score(
-                validator_update.fee,
-                validator_update.is_active,
-                validator_update.decay_factor,
-            );
-
-            let validator_data = ValidatorData {
-                fee: validator_update.fee,
-                is_active: validator_update.is_active,
-                decay_factor: validator_update.decay_factor,
-                p_score,
-                updated_era: current_era,
-            };
-
-            self.validators.set(&validator_update.pubkey, validator_data);
+        for validator_update in validators_data.iter() {
+            self.write_validator(validator_update, stake_total, current_era);
         }
 
-        self.network_p_avg.set(p_avg);
+        let p_avg = self.network_average.p_avg(self.get_p_avg_mode());
+        let p_avg = self.record_p_avg(current_era, p_avg);
 
         if current_era > last_era {
             self.last_update_era.set(current_era);
         }
+        self.last_update_time.set(self.env().get_block_time());
 
         self.env().emit_event(ValidatorsUpdated {
             era: current_era,
@@ -120,8 +183,257 @@ impl ValidatorRegistry {
         });
     }
 
+    /// Commits one Merkle root over every validator's data for the era
+    /// instead of writing each validator. Leaves are
+    /// `blake2b(0x00 || era || ValidatorUpdateData)` and parents hash their
+    /// sorted children as `blake2b(0x01 || low || high)`; anyone can then
+    /// bring a validator's data on chain with `prove_validator`.
+    /// `totals` are the era's p_avg sums over every leaf. They only set the
+    /// p_avg once every leaf has been proven and the proven sums match them.
+    pub fn commit_scores(&mut self, root: Hash, count: u32, totals: NetworkTotals, current_era: u64) {
+        self.require_keeper();
+        self.record_report(current_era);
+        self.require_era_bound(current_era, true);
+
+        if count == 0 {
+            self.env().revert(Error::EmptyBatch);
+        }
+        if totals.count > count as u64 {
+            self.env().revert(Error::InvalidTotals);
+        }
+        if current_era < self.last_update_era.get_or_default() {
+            self.env().revert(Error::InvalidEra);
+        }
+        if self.score_commits.get(&current_era).is_some() {
+            self.env().revert(Error::ScoresAlreadyCommitted);
+        }
+
+        self.score_commits.set(&current_era, ScoreCommit {
+            root,
+            count,
+            totals,
+            proven: 0,
+            proven_totals: NetworkTotals::default(),
+        });
+        self.last_update_era.set(current_era);
+        self.last_update_time.set(self.env().get_block_time());
+        self.env().emit_event(ScoresCommitted {
+            era: current_era,
+            root,
+            count,
+        });
+    }
+
+    /// Stores a validator's data for a committed era, given a Merkle proof
+    /// against that era's root
+    /// Roots stay provable until their data would be stale, so proofs built
+    /// just before a new commit still land. Proven data is cached: later
+    /// calls for the same era return it as is. Stake shares are taken against
+    /// the committed stake sum, which the era's last proof checks.
+    pub fn prove_validator(&mut self, era: u64, data: ValidatorUpdateData, proof: Vec<Hash>) -> ValidatorData {
+        let mut commit = self.score_commits.get(&era).unwrap_or_revert_with(&self.env(), Error::NoCommittedRoot);
+        if self.last_update_era.get_or_default() - era > STALE_DATA_ERAS {
+            self.env().revert(Error::RootExpired);
+        }
+
+        let existing = self.stored(&data.pubkey);
+        if let Some(cached) = existing.clone().filter(|v| v.updated_era == era) {
+            return cached;
+        }
+        if existing.is_some_and(|v| v.updated_era > era) {
+            self.env().revert(Error::InvalidEra);
+        }
+
+        let env = self.env();
+        let leaf = env.hash(merkle::leaf_preimage(era, &data));
+        if merkle::root_from_proof(leaf, &proof, |bytes| env.hash(bytes)) != commit.root {
+            self.env().revert(Error::InvalidProof);
+        }
+
+        let proven = self.write_validator(&data, commit.totals.stake_sum, era);
+        self.env().emit_event(ValidatorProven {
+            pubkey: data.pubkey,
+            era,
+            p_score: proven.p_score,
+        });
+
+        commit.proven += 1;
+        commit.proven_totals.add(&proven);
+        self.score_commits.set(&era, commit.clone());
+        if commit.proven == commit.count {
+            self.settle_commit(era, commit);
+        }
+        proven
+    }
+
+    pub fn get_score_root(&self, era: u64) -> Option<Hash> {
+        self.score_commits.get(&era).map(|commit| commit.root)
+    }
+
+    pub fn get_score_commit(&self, era: u64) -> Option<ScoreCommit> {
+        self.score_commits.get(&era)
+    }
+
     pub fn get_validator(&self, pubkey: PublicKey) -> Option<ValidatorData> {
-        self.validators.get(&pubkey)
+        self.stored(&pubkey)
+    }
+
+    /// Moves a validator through its lifecycle
+    /// Updates already promote and demote on their own; this covers the
+    /// rest. The keeper may promote candidates and move validators in and
+    /// out of probation; jailing, unjailing and retirement need the owner.
+    /// Dwell times count registry eras, so callers cannot backdate them.
+    pub fn set_validator_state(&mut self, pubkey: PublicKey, state: ValidatorState) {
+        self.require_keeper();
+        let mut data = self.stored(&pubkey).unwrap_or_revert_with(&self.env(), Error::ValidatorNotFound);
+
+        if !data.state.can_move_to(state) {
+            self.env().revert(Error::InvalidTransition);
+        }
+        if !data.state.keeper_may_move_to(state) && Some(self.env().caller()) != self.owner.get() {
+            self.env().revert(Error::Unauthorized);
+        }
+
+        let era = self.last_update_era.get_or_default();
+        let dwell = self.get_lifecycle_config().dwell(data.state, state);
+        if era < data.state_since + dwell {
+            self.env().revert(Error::DwellNotElapsed);
+        }
+
+        let old = data.clone();
+        self.move_state(&pubkey, &mut data, state, era);
+        self.network_average.replace(Some(old), Some(data.clone()));
+        self.history.record_validator(&pubkey, era, data.clone());
+        self.validators.set(&pubkey, data);
+        self.refresh_p_avg(era);
+    }
+
+    /// Drops a retired validator once its dwell has passed
+    /// Anyone may call this; the entry disappears from lookups and pages.
+    pub fn prune_validator(&mut self, pubkey: PublicKey) {
+        let data = self.stored(&pubkey).unwrap_or_revert_with(&self.env(), Error::ValidatorNotFound);
+        if data.state != ValidatorState::Retired {
+            self.env().revert(Error::InvalidTransition);
+        }
+
+        let era = self.last_update_era.get_or_default();
+        if era < data.state_since + self.get_lifecycle_config().retired_eras {
+            self.env().revert(Error::DwellNotElapsed);
+        }
+
+        self.validator_set.remove(&pubkey);
+        self.env().emit_event(ValidatorPruned { pubkey, era });
+    }
+
+    pub fn get_lifecycle_config(&self) -> LifecycleConfig {
+        self.lifecycle.get_or_default()
+    }
+
+    pub fn set_lifecycle_config(&mut self, config: LifecycleConfig) {
+        self.require_owner();
+        self.lifecycle.set(config);
+    }
+
+    pub fn get_score_formula_version(&self) -> u32 {
+        self.scoring.current_version()
+    }
+
+    pub fn get_score_formula(&self, version: u32) -> Option<ScoreFormula> {
+        self.scoring.formula(version)
+    }
+
+    /// Publishes `formula` as a new version that scores every later update
+    /// Existing scores keep their version until the validator is rewritten.
+    pub fn set_score_formula(&mut self, formula: ScoreFormula) -> u32 {
+        self.require_owner();
+        let version = self.scoring.publish(formula)
+            .unwrap_or_revert_with(&self.env(), Error::InvalidFormula);
+        self.env().emit_event(ScoreFormulaPublished { version });
+        version
+    }
+
+    /// What `version` would score `inputs` as, without writing anything
+    pub fn preview_p_score(&self, inputs: ScoreInputs, version: u32) -> u64 {
+        self.scoring.score(version, inputs)
+            .unwrap_or_revert_with(&self.env(), Error::UnknownFormula)
+    }
+
+    /// Era from which a validator flagged for a commission spike is valid again
+    pub fn get_commission_flag(&self, pubkey: PublicKey) -> Option<u64> {
+        self.commission.flagged_until(&pubkey)
+    }
+
+    pub fn get_fee_change_count(&self, pubkey: PublicKey) -> u32 {
+        self.commission.fee_change_count(&pubkey)
+    }
+
+    /// The validator's `index`th fee change, oldest first
+    pub fn get_fee_change(&self, pubkey: PublicKey, index: u32) -> Option<FeeChange> {
+        self.commission.fee_change(&pubkey, index)
+    }
+
+    pub fn get_commission_config(&self) -> CommissionConfig {
+        self.commission.config()
+    }
+
+    pub fn set_commission_config(&mut self, config: CommissionConfig) {
+        self.require_owner();
+        if config.lookback_eras == 0 {
+            self.env().revert(Error::InvalidConfig);
+        }
+        self.commission.set_config(config);
+    }
+
+    pub fn get_p_avg_mode(&self) -> PAvgMode {
+        self.p_avg_mode.get_or_default()
+    }
+
+    /// Switches how validators are weighted in the network p_avg
+    /// Both weightings are kept up to date, so the switch applies at once.
+    pub fn set_p_avg_mode(&mut self, mode: PAvgMode) {
+        self.require_owner();
+        self.p_avg_mode.set(mode);
+        self.refresh_p_avg(self.last_update_era.get_or_default());
+    }
+
+    pub fn get_validator_count(&self) -> u32 {
+        self.validator_set.len()
+    }
+
+    /// Up to `limit` validators after skipping `offset`, at most 100 per call
+    pub fn get_validators(&self, offset: u32, limit: u32) -> Vec<ValidatorEntry> {
+        self.validator_set
+            .page(offset, limit.min(MAX_PAGE_SIZE))
+            .into_iter()
+            .filter_map(|pubkey| self.entry(pubkey))
+            .collect()
+    }
+
+    /// Like `get_validators`, counting only active validators with a score
+    pub fn get_active_validators(&self, offset: u32, limit: u32) -> Vec<ValidatorEntry> {
+        self.active_entries()
+            .skip(offset as usize)
+            .take(limit.min(MAX_PAGE_SIZE) as usize)
+            .collect()
+    }
+
+    /// The `n` active validators with the highest p_score, best first
+    pub fn get_top_validators(&self, n: u32) -> Vec<ValidatorEntry> {
+        let mut entries: Vec<ValidatorEntry> = self.active_entries().collect();
+        entries.sort_by(|a, b| b.data.p_score.cmp(&a.data.p_score));
+        entries.truncate(n.min(MAX_PAGE_SIZE) as usize);
+        entries
+    }
+
+    /// The validator's data as it stood at `era`, from its latest update
+    /// at or before that era
+    pub fn get_validator_at(&self, pubkey: PublicKey, era: u64) -> Option<ValidatorData> {
+        self.history.validator_at(&pubkey, era)
+    }
+
+    /// Network p_avg as it stood at `era`
+    pub fn get_p_avg_at(&self, era: u64) -> Option<u64> {
+        self.history.p_avg_at(era)
     }
 
     pub fn get_network_p_avg(&self) -> u64 {
@@ -132,13 +444,28 @@ impl ValidatorRegistry {
         self.last_update_era.get_or_default()
     }
 
+    /// Whether stake may go to the validator
+    /// Validators flagged for a commission spike stay invalid until their
+    /// cool-off has passed in registry eras.
     pub fn is_valid(&self, pubkey: PublicKey, current_era: u64) -> bool {
-        let validator_opt = self.validators.get(&pubkey);
+        if self.commission.is_flagged(&pubkey, self.last_update_era.get_or_default()) {
+            return false;
+        }
+        let validator_opt = self.stored(&pubkey);
+        let last_era = self.last_update_era.get_or_default();
+
+        // Committed eras only bring data on chain once proven
+        if self.score_commits.get(&last_era).is_some()
+            && validator_opt.as_ref().is_some_and(|v| last_era - v.updated_era > STALE_DATA_ERAS)
+        {
+            return false;
+        }
 
         match validator_opt {
             Some(v) => {
                 v.p_score > 0
                     && v.is_active
+                    && v.state == ValidatorState::Active
                     && (current_era - self.last_update_era.get_or_default()) <= STALE_DATA_ERAS
             }
             None => false,
@@ -150,14 +477,186 @@ impl ValidatorRegistry {
         self.keeper.set(new_keeper);
     }
 
-    fn calculate_p_score(&self, fee: u64, is_active: bool, decay_factor: u64) -> u64 {
-        if !is_active {
-            return 0;
+    /// Lets keepers bonded in `keeper_bonds` submit updates
+    /// The registry must be registered there as a reporter.
+    pub fn set_keeper_bonds(&mut self, keeper_bonds: Address) {
+        self.require_owner();
+        self.keeper_bonds.set(keeper_bonds);
+    }
+
+    /// Stored data, ignoring validators that have been pruned
+    fn stored(&self, pubkey: &PublicKey) -> Option<ValidatorData> {
+        if !self.validator_set.contains(pubkey) {
+            return None;
         }
+        self.validators.get(pubkey)
+    }
 
-        let fee_component = 100u64.saturating_sub(fee);
+    fn entry(&self, pubkey: PublicKey) -> Option<ValidatorEntry> {
+        self.stored(&pubkey).map(|data| ValidatorEntry { pubkey, data })
+    }
+
+    /// Validates, scores and stores one validator's data for `current_era`
+    fn write_validator(&mut self, update: &ValidatorUpdateData, stake_total: U512, current_era: u64) -> ValidatorData {
+        let lifecycle = self.get_lifecycle_config();
+        let score_version = self.scoring.current_version();
+
+        if update.fee > 100 {
+            self.env().revert(Error::InvalidFee);
+        }
+
+        if update.decay_factor > 100 {
+            self.env().revert(Error::InvalidDecayFactor);
+        }
 
-        fee_component.saturating_mul(decay_factor) / 100
+        if update.uptime > 100 {
+            self.env().revert(Error::InvalidUptime);
+        }
+
+        if update.self_bond > update.delegated_stake {
+            self.env().revert(Error::InvalidSelfBond);
+        }
+
+        // Stake beyond the self-bond needs delegators, and delegators need stake
+        let has_delegations = update.delegated_stake > update.self_bond;
+        if has_delegations != (update.delegator_count > 0) {
+            self.env().revert(Error::InvalidDelegatorCount);
+        }
+
+        if update.last_fee_change_era > current_era {
+            self.env().revert(Error::InvalidFeeChangeEra);
+        }
+
+        let existing = self.stored(&update.pubkey);
+        if existing.as_ref().is_some_and(|v| v.updated_era == current_era) {
+            self.env().revert(Error::ValidatorAlreadyUpdated);
+        }
+
+        if let Some(from_fee) = self.commission.record(&update.pubkey, current_era, update.fee) {
+            self.env().emit_event(CommissionSpike {
+                pubkey: update.pubkey.clone(),
+                era: current_era,
+                from_fee,
+                to_fee: update.fee,
+                flagged_until: self.commission.flagged_until(&update.pubkey).unwrap_or_default(),
+            });
+        }
+
+        let inputs = ScoreInputs {
+            fee: update.fee,
+            is_active: update.is_active,
+            decay_factor: update.decay_factor,
+            uptime: update.uptime,
+            stake_share_bps: network_average::stake_share_bps(update.delegated_stake, stake_total),
+            commission_stability: commission_stability(existing.as_ref(), update.fee),
+        };
+        let p_score = self.scoring.score(score_version, inputs).unwrap_or_default();
+
+        let (state, state_since) = existing
+            .as_ref()
+            .map(|v| (v.state, v.state_since))
+            .unwrap_or((ValidatorState::Candidate, current_era));
+
+        let mut validator_data = ValidatorData {
+            fee: update.fee,
+            is_active: update.is_active,
+            decay_factor: update.decay_factor,
+            p_score,
+            updated_era: current_era,
+            state,
+            state_since,
+            delegated_stake: update.delegated_stake,
+            score_version,
+            uptime: update.uptime,
+            delegator_count: update.delegator_count,
+            self_bond: update.self_bond,
+            last_fee_change_era: update.last_fee_change_era,
+        };
+
+        // An active validator that drops out is on probation, not gone;
+        // candidates and recovered validators come back once their dwell is up
+        let promotable = matches!(state, ValidatorState::Candidate | ValidatorState::Probation)
+            && current_era >= state_since + lifecycle.dwell(state, ValidatorState::Active);
+        if state == ValidatorState::Active && !update.is_active {
+            self.move_state(&update.pubkey, &mut validator_data, ValidatorState::Probation, current_era);
+        } else if promotable && update.is_active {
+            self.move_state(&update.pubkey, &mut validator_data, ValidatorState::Active, current_era);
+        }
+
+        if existing.as_ref().map(|v| v.p_score) != Some(p_score) {
+            let (old_fee, old_p_score) = existing.as_ref().map(|v| (v.fee, v.p_score)).unwrap_or_default();
+            self.env().emit_event(ValidatorScoreChanged {
+                pubkey: update.pubkey.clone(),
+                era: current_era,
+                old_fee,
+                new_fee: validator_data.fee,
+                old_p_score,
+                new_p_score: validator_data.p_score,
+            });
+        }
+
+        self.network_average.replace(existing, Some(validator_data.clone()));
+        self.validator_set.insert(&update.pubkey);
+        self.history.record_validator(&update.pubkey, current_era, validator_data.clone());
+        self.validators.set(&update.pubkey, validator_data.clone());
+        validator_data
+    }
+
+    /// Recomputes the network p_avg, from the era's committed totals if it
+    /// has them and from the running sums otherwise
+    fn refresh_p_avg(&mut self, era: u64) -> u64 {
+        let mode = self.get_p_avg_mode();
+        let p_avg = match self.score_commits.get(&era).filter(|commit| commit.is_verified()) {
+            Some(commit) => commit.totals.p_avg(mode),
+            None => self.network_average.p_avg(mode),
+        };
+        self.record_p_avg(era, p_avg)
+    }
+
+    /// Checks a fully proven commit's sums against the keeper's; matching
+    /// sums set the p_avg if the era is still the latest
+    fn settle_commit(&mut self, era: u64, commit: ScoreCommit) {
+        if !commit.is_verified() {
+            self.env().emit_event(ScoresMismatched { era });
+            return;
+        }
+
+        let p_avg = if era == self.last_update_era.get_or_default() {
+            self.refresh_p_avg(era)
+        } else {
+            self.get_network_p_avg()
+        };
+        self.env().emit_event(ScoresVerified { era, p_avg });
+    }
+
+    /// Kept within [10, 100] so the staking multiplier stays bounded; with
+    /// nothing to average the previous value stands.
+    fn record_p_avg(&mut self, era: u64, p_avg: Option<u64>) -> u64 {
+        let p_avg = match p_avg {
+            Some(p_avg) => p_avg.clamp(MIN_P_AVG, MAX_P_AVG),
+            None => self.get_network_p_avg(),
+        };
+        self.network_p_avg.set(p_avg);
+        self.history.record_p_avg(era, p_avg);
+        p_avg
+    }
+
+    fn move_state(&self, pubkey: &PublicKey, data: &mut ValidatorData, to: ValidatorState, era: u64) {
+        self.env().emit_event(ValidatorStateChanged {
+            pubkey: pubkey.clone(),
+            from: data.state,
+            to,
+            era,
+        });
+        data.state = to;
+        data.state_since = era;
+    }
+
+    fn active_entries(&self) -> impl Iterator<Item = ValidatorEntry> + '_ {
+        (0..self.validator_set.len())
+            .filter_map(|index| self.validator_set.get(index))
+            .filter_map(|pubkey| self.entry(pubkey))
+            .filter(|entry| entry.data.is_active && entry.data.p_score > 0 && entry.data.state == ValidatorState::Active)
     }
 
     fn require_keeper(&self) {
@@ -170,6 +669,57 @@ impl ValidatorRegistry {
         }
     }
 
+    /// Like `require_keeper`, but bonded keepers may also submit reports.
+    /// Returns false for bonded keepers
+    fn require_reporter(&self) -> bool {
+        let caller = self.env().caller();
+        let keeper = self.keeper.get_or_revert_with(Error::NotInitialized);
+        let owner = self.owner.get_or_revert_with(Error::NotInitialized);
+
+        if caller == keeper || caller == owner {
+            return true;
+        }
+        if !self.keeper_bonds().is_some_and(|bonds| bonds.is_bonded(caller)) {
+            self.env().revert(Error::Unauthorized);
+        }
+        false
+    }
+
+    /// Reported eras cannot run ahead of the block time since the last
+    /// report, and only the keeper may set the first one
+    fn require_era_bound(&self, current_era: u64, trusted: bool) {
+        let Some(last_time) = self.last_update_time.get() else {
+            if !trusted {
+                self.env().revert(Error::Unauthorized);
+            }
+            return;
+        };
+
+        let elapsed = self.env().get_block_time().saturating_sub(last_time);
+        let max_era = self.last_update_era.get_or_default()
+            .saturating_add(elapsed / ERA_DURATION_MS)
+            .saturating_add(1);
+        if current_era > max_era {
+            self.env().revert(Error::InvalidEra);
+        }
+    }
+
+    fn keeper_bonds(&self) -> Option<KeeperBondsContractContractRef> {
+        self.keeper_bonds.get()
+            .map(|address| KeeperBondsContractContractRef::new(self.env(), address))
+    }
+
+    /// Logs a keeper report so bonded keepers can dispute it
+    fn record_report(&mut self, era: u64) {
+        let caller = self.env().caller();
+        if Some(caller) == self.owner.get() {
+            return;
+        }
+        if let Some(mut bonds) = self.keeper_bonds() {
+            bonds.record_report(caller, era);
+        }
+    }
+
     fn require_owner(&self) {
         let caller = self.env().caller();
         let owner = self.owner.get_or_revert_with(Error::NotInitialized);
@@ -187,6 +737,79 @@ pub struct ValidatorsUpdated {
     pub p_avg: u64,
 }
 
+/// A validator's p_score moved; old values are zero on its first update
+#[odra::event]
+pub struct ValidatorScoreChanged {
+    pub pubkey: PublicKey,
+    pub era: u64,
+    pub old_fee: u64,
+    pub new_fee: u64,
+    pub old_p_score: u64,
+    pub new_p_score: u64,
+}
+
+#[odra::event]
+pub struct ValidatorStateChanged {
+    pub pubkey: PublicKey,
+    pub from: ValidatorState,
+    pub to: ValidatorState,
+    pub era: u64,
+}
+
+#[odra::event]
+pub struct ValidatorPruned {
+    pub pubkey: PublicKey,
+    pub era: u64,
+}
+
+#[odra::event]
+pub struct ScoreFormulaPublished {
+    pub version: u32,
+}
+
+/// A fee rose from `from_fee`, the lowest within the lookback, by more than
+/// the configured threshold
+#[odra::event]
+pub struct CommissionSpike {
+    pub pubkey: PublicKey,
+    pub era: u64,
+    pub from_fee: u64,
+    pub to_fee: u64,
+    pub flagged_until: u64,
+}
+
+/// 100 unless the fee rose since the last update, minus 10 per point it rose
+fn commission_stability(existing: Option<&ValidatorData>, fee: u64) -> u64 {
+    let rise = existing.map(|v| fee.saturating_sub(v.fee)).unwrap_or_default();
+    100u64.saturating_sub(rise.saturating_mul(10))
+}
+
+#[odra::event]
+pub struct ScoresCommitted {
+    pub era: u64,
+    pub root: Hash,
+    pub count: u32,
+}
+
+#[odra::event]
+pub struct ScoresVerified {
+    pub era: u64,
+    pub p_avg: u64,
+}
+
+/// The commit's sums and what its leaves add up to are in `get_score_commit`
+#[odra::event]
+pub struct ScoresMismatched {
+    pub era: u64,
+}
+
+#[odra::event]
+pub struct ValidatorProven {
+    pub pubkey: PublicKey,
+    pub era: u64,
+    pub p_score: u64,
+}
+
 #[odra::odra_error]
 pub enum Error {
     Unauthorized = 1,
@@ -199,13 +822,29 @@ pub enum Error {
     DuplicateValidator = 8,
     EmptyBatch = 9,
     ValidatorAlreadyUpdated = 10,
+    ValidatorNotFound = 11,
+    InvalidTransition = 12,
+    DwellNotElapsed = 13,
+    InvalidFormula = 14,
+    UnknownFormula = 15,
+    InvalidUptime = 16,
+    InvalidSelfBond = 17,
+    InvalidDelegatorCount = 18,
+    InvalidFeeChangeEra = 19,
+    InvalidConfig = 20,
+    NoCommittedRoot = 21,
+    InvalidProof = 22,
+    ScoresAlreadyCommitted = 23,
+    RootExpired = 24,
+    InvalidTotals = 25,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use odra::casper_types::AsymmetricType;
-    use odra::host::{Deployer, HostEnv};
+    use odra::casper_types::{AsymmetricType, U512};
+    use keeper_bonds::{KeeperBonds, KeeperBondsInitArgs};
+    use odra::host::{Deployer, HostEnv, HostRef};
 
     fn setup() -> (HostEnv, ValidatorRegistryHostRef, Address, Address) {
         let env = odra_test::env();
@@ -240,6 +879,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn test_update_validators() {
         let (env, mut registry, _owner, keeper) = setup();
 
@@ -251,15 +891,20 @@ mod tests {
             fee: 5u64,
             is_active: true,
             decay_factor: 100u64,
+            delegated_stake: U512::zero(),
+            uptime: 100,
+            delegator_count: 0,
+            self_bond: U512::zero(),
+            last_fee_change_era: 0,
         }];
 
-        registry.update_validators(validators, 85, 1);
+        registry.update_validators(validators, 1);
 
         let validator_data = registry.get_validator(pubkey).unwrap();
         assert_eq!(validator_data.fee, 5);
         assert_eq!(validator_data.is_active, true);
         assert_eq!(validator_data.p_score, 95);
-        assert_eq!(registry.get_network_p_avg(), 85);
+        assert_eq!(registry.get_network_p_avg(), 95);
         assert_eq!(registry.get_last_update_era(), 1);
     }
 
@@ -277,9 +922,14 @@ mod tests {
             fee: 5u64,
             is_active: true,
             decay_factor: 100u64,
+            delegated_stake: U512::zero(),
+            uptime: 100,
+            delegator_count: 0,
+            self_bond: U512::zero(),
+            last_fee_change_era: 0,
         }];
 
-        registry.update_validators(validators, 85, 1);
+        registry.update_validators(validators, 1);
     }
 
     #[test]
@@ -294,11 +944,16 @@ mod tests {
             fee: 10u64,
             is_active: true,
             decay_factor: 100u64,
+            delegated_stake: U512::zero(),
+            uptime: 100,
+            delegator_count: 0,
+            self_bond: U512::zero(),
+            last_fee_change_era: 0,
         }];
 
-        registry.update_validators(validators, 85, 1);
+        registry.update_validators(validators, 1);
 
-        assert_eq!(registry.get_network_p_avg(), 85);
+        assert_eq!(registry.get_network_p_avg(), 90);
         assert_eq!(registry.get_last_update_era(), 1);
     }
 
@@ -308,7 +963,7 @@ mod tests {
         env.set_caller(keeper);
 
         let pubkey1 = create_test_pubkey(1);
-        let pubkey2 = create_test_pubkey(2);
+        let pubkey2 = create_test_pubkey(6);
         let pubkey3 = create_test_pubkey(3);
 
         registry.update_validators(
@@ -318,21 +973,35 @@ mod tests {
                     fee: 5u64,
                     is_active: true,
                     decay_factor: 100u64,
+                    delegated_stake: U512::zero(),
+                    uptime: 100,
+                    delegator_count: 0,
+                    self_bond: U512::zero(),
+                    last_fee_change_era: 0,
                 },
                 ValidatorUpdateData {
                     pubkey: pubkey2.clone(),
                     fee: 10u64,
                     is_active: true,
                     decay_factor: 100u64,
+                    delegated_stake: U512::zero(),
+                    uptime: 100,
+                    delegator_count: 0,
+                    self_bond: U512::zero(),
+                    last_fee_change_era: 0,
                 },
                 ValidatorUpdateData {
                     pubkey: pubkey3.clone(),
                     fee: 15u64,
                     is_active: true,
                     decay_factor: 95u64,
+                    delegated_stake: U512::zero(),
+                    uptime: 100,
+                    delegator_count: 0,
+                    self_bond: U512::zero(),
+                    last_fee_change_era: 0,
                 },
             ],
-            88,
             1,
         );
 
@@ -357,16 +1026,26 @@ mod tests {
                 fee: 5u64,
                 is_active: true,
                 decay_factor: 100u64,
+                delegated_stake: U512::zero(),
+                uptime: 100,
+                delegator_count: 0,
+                self_bond: U512::zero(),
+                last_fee_change_era: 0,
             },
             ValidatorUpdateData {
                 pubkey: pubkey.clone(),
                 fee: 10u64,
                 is_active: true,
                 decay_factor: 100u64,
+                delegated_stake: U512::zero(),
+                uptime: 100,
+                delegator_count: 0,
+                self_bond: U512::zero(),
+                last_fee_change_era: 0,
             },
         ];
 
-        registry.update_validators(validators, 85, 1);
+        registry.update_validators(validators, 1);
     }
 
     #[test]
@@ -382,18 +1061,28 @@ mod tests {
             fee: 5u64,
             is_active: true,
             decay_factor: 100u64,
+            delegated_stake: U512::zero(),
+            uptime: 100,
+            delegator_count: 0,
+            self_bond: U512::zero(),
+            last_fee_change_era: 0,
         }];
 
-        registry.update_validators(batch1, 85, 10);
+        registry.update_validators(batch1, 10);
 
         let batch2 = vec![ValidatorUpdateData {
             pubkey: pubkey.clone(),
             fee: 10u64,
             is_active: true,
             decay_factor: 100u64,
+            delegated_stake: U512::zero(),
+            uptime: 100,
+            delegator_count: 0,
+            self_bond: U512::zero(),
+            last_fee_change_era: 0,
         }];
 
-        registry.update_validators(batch2, 90, 5);
+        registry.update_validators(batch2, 5);
     }
 
     #[test]
@@ -409,18 +1098,28 @@ mod tests {
             fee: 5u64,
             is_active: true,
             decay_factor: 100u64,
+            delegated_stake: U512::zero(),
+            uptime: 100,
+            delegator_count: 0,
+            self_bond: U512::zero(),
+            last_fee_change_era: 0,
         }];
 
-        registry.update_validators(batch1, 85, 1);
+        registry.update_validators(batch1, 1);
 
         let batch2 = vec![ValidatorUpdateData {
             pubkey: pubkey.clone(),
             fee: 10u64,
             is_active: true,
             decay_factor: 90u64,
+            delegated_stake: U512::zero(),
+            uptime: 100,
+            delegator_count: 0,
+            self_bond: U512::zero(),
+            last_fee_change_era: 0,
         }];
 
-        registry.update_validators(batch2, 90, 1);
+        registry.update_validators(batch2, 1);
     }
 
     #[test]
@@ -430,7 +1129,7 @@ mod tests {
         env.set_caller(keeper);
 
         let validators: Vec<ValidatorUpdateData> = vec![];
-        registry.update_validators(validators, 85, 1);
+        registry.update_validators(validators, 1);
     }
 
     #[test]
@@ -439,7 +1138,7 @@ mod tests {
         env.set_caller(keeper);
 
         let pubkey1 = create_test_pubkey(1);
-        let pubkey2 = create_test_pubkey(2);
+        let pubkey2 = create_test_pubkey(6);
 
         registry.update_validators(
             vec![
@@ -448,15 +1147,24 @@ mod tests {
                     fee: 5u64,
                     is_active: true,
                     decay_factor: 100u64,
+                    delegated_stake: U512::zero(),
+                    uptime: 100,
+                    delegator_count: 0,
+                    self_bond: U512::zero(),
+                    last_fee_change_era: 0,
                 },
                 ValidatorUpdateData {
                     pubkey: pubkey2.clone(),
                     fee: 10u64,
                     is_active: true,
                     decay_factor: 100u64,
+                    delegated_stake: U512::zero(),
+                    uptime: 100,
+                    delegator_count: 0,
+                    self_bond: U512::zero(),
+                    last_fee_change_era: 0,
                 },
             ],
-            85,
             1,
         );
 
@@ -471,7 +1179,7 @@ mod tests {
         env.set_caller(keeper);
 
         let pubkey1 = create_test_pubkey(1);
-        let pubkey2 = create_test_pubkey(2);
+        let pubkey2 = create_test_pubkey(6);
 
         registry.update_validators(
             vec![ValidatorUpdateData {
@@ -479,8 +1187,12 @@ mod tests {
                 fee: 5u64,
                 is_active: true,
                 decay_factor: 100u64,
+                delegated_stake: U512::zero(),
+                uptime: 100,
+                delegator_count: 0,
+                self_bond: U512::zero(),
+                last_fee_change_era: 0,
             }],
-            85,
             1,
         );
 
@@ -490,8 +1202,12 @@ mod tests {
                 fee: 10u64,
                 is_active: true,
                 decay_factor: 90u64,
+                delegated_stake: U512::zero(),
+                uptime: 100,
+                delegator_count: 0,
+                self_bond: U512::zero(),
+                last_fee_change_era: 0,
             }],
-            90,
             2,
         );
 
@@ -499,4 +1215,766 @@ mod tests {
         assert_eq!(registry.get_validator(pubkey2).unwrap().updated_era, 2);
         assert_eq!(registry.get_last_update_era(), 2);
     }
+
+    #[test]
+    fn test_bonded_keeper_updates_are_logged() {
+        let (env, mut registry, owner, keeper) = setup();
+        let bonded = env.get_account(2);
+
+        let mut bonds = KeeperBonds::deploy(&env, KeeperBondsInitArgs { yscspr_token: owner });
+        bonds.set_reporter(registry.address(), true);
+        registry.set_keeper_bonds(bonds.address());
+
+        let batch = vec![ValidatorUpdateData {
+            pubkey: create_test_pubkey(1),
+            fee: 5u64,
+            is_active: true,
+            decay_factor: 100u64,
+            delegated_stake: U512::zero(),
+            uptime: 100,
+            delegator_count: 0,
+            self_bond: U512::zero(),
+            last_fee_change_era: 0,
+        }];
+
+        env.set_caller(bonded);
+        assert_eq!(
+            registry.try_update_validators(batch.clone(), 1),
+            Err(Error::Unauthorized.into())
+        );
+
+        // The keeper sets the first era, bonded keepers then follow the block time
+        bonds.with_tokens(U512::from(10_000_000_000_000u64)).bond();
+        assert_eq!(
+            registry.try_update_validators(batch.clone(), 1),
+            Err(Error::Unauthorized.into())
+        );
+        env.set_caller(keeper);
+        registry.update_validators(batch.clone(), 1);
+        env.set_caller(bonded);
+        assert_eq!(
+            registry.try_update_validators(batch.clone(), u64::MAX),
+            Err(Error::InvalidEra.into())
+        );
+        env.advance_block_time(2 * ERA_DURATION_MS);
+        assert_eq!(
+            registry.try_update_validators(batch.clone(), 5),
+            Err(Error::InvalidEra.into())
+        );
+        registry.update_validators(batch, 4);
+
+        let report = bonds.get_report(2).unwrap();
+        assert_eq!((report.reporter, report.source, report.era), (bonded, registry.address(), 4));
+
+        // Bonded keepers only submit updates
+        assert_eq!(
+            registry.try_set_validator_state(create_test_pubkey(1), ValidatorState::Retired),
+            Err(Error::Unauthorized.into())
+        );
+    }
+
+    #[test]
+    fn test_history_survives_later_updates() {
+        let (env, mut registry, _owner, keeper) = setup();
+        env.set_caller(keeper);
+        let pubkey = create_test_pubkey(1);
+        let update = |fee: u64| vec![ValidatorUpdateData {
+            pubkey: pubkey.clone(),
+            fee,
+            is_active: true,
+            decay_factor: 100u64,
+            delegated_stake: U512::zero(),
+            uptime: 100,
+            delegator_count: 0,
+            self_bond: U512::zero(),
+            last_fee_change_era: 0,
+        }];
+
+        registry.update_validators(update(5), 3);
+        assert!(env.emitted_event(&registry, ValidatorScoreChanged {
+            pubkey: pubkey.clone(),
+            era: 3,
+            old_fee: 0,
+            new_fee: 5,
+            old_p_score: 0,
+            new_p_score: 95,
+        }));
+        env.advance_block_time(ERA_DURATION_MS);
+        registry.update_validators(update(5), 5);
+        env.advance_block_time(2 * ERA_DURATION_MS);
+        registry.update_validators(update(20), 8);
+
+        assert!(env.emitted_event(&registry, ValidatorScoreChanged {
+            pubkey: pubkey.clone(),
+            era: 8,
+            old_fee: 5,
+            new_fee: 20,
+            old_p_score: 95,
+            new_p_score: 80,
+        }));
+        // Era 5 kept the same score, so only two changes were emitted
+        // alongside the promotion out of candidacy and the fee spike
+        assert_eq!(env.events_count(&registry), 7);
+
+        assert!(registry.get_validator_at(pubkey.clone(), 2).is_none());
+        assert_eq!(registry.get_validator_at(pubkey.clone(), 4).unwrap().updated_era, 3);
+        assert_eq!(registry.get_validator_at(pubkey.clone(), 7).unwrap().p_score, 95);
+        assert_eq!(registry.get_validator_at(pubkey.clone(), 100).unwrap().p_score, 80);
+
+        assert_eq!(registry.get_p_avg_at(2), None);
+        assert_eq!(registry.get_p_avg_at(3), Some(95));
+        assert_eq!(registry.get_p_avg_at(7), Some(95));
+        assert_eq!(registry.get_p_avg_at(8), Some(80));
+    }
+
+    #[test]
+    fn test_validator_set_is_enumerable() {
+        let (env, mut registry, _owner, keeper) = setup();
+        env.set_caller(keeper);
+
+        let updates: Vec<ValidatorUpdateData> = (1..=5u8)
+            .zip([1u8, 6, 3, 9, 10])
+            .map(|(i, byte)| ValidatorUpdateData {
+                pubkey: create_test_pubkey(byte),
+                fee: 10 * i as u64,
+                is_active: i != 3,
+                decay_factor: 100u64,
+                delegated_stake: U512::zero(),
+                uptime: 100,
+                delegator_count: 0,
+                self_bond: U512::zero(),
+                last_fee_change_era: 0,
+            })
+            .collect();
+        registry.update_validators(updates.clone(), 1);
+
+        // Candidates are not listed as active until promoted
+        assert!(registry.get_active_validators(0, 10).is_empty());
+        env.advance_block_time(ERA_DURATION_MS);
+        registry.update_validators(updates.clone(), 3);
+
+        // Updating known validators does not grow the set
+        registry.update_validators(updates[..2].to_vec(), 4);
+        assert_eq!(registry.get_validator_count(), 5);
+
+        let page = registry.get_validators(1, 2);
+        assert_eq!(page.len(), 2);
+        assert_eq!(page[0].pubkey, create_test_pubkey(6));
+        assert_eq!(page[1].pubkey, create_test_pubkey(3));
+        assert!(registry.get_validators(5, 10).is_empty());
+
+        let active: Vec<PublicKey> = registry.get_active_validators(1, 10).into_iter().map(|e| e.pubkey).collect();
+        assert_eq!(active, vec![create_test_pubkey(6), create_test_pubkey(9), create_test_pubkey(10)]);
+
+        let top = registry.get_top_validators(2);
+        assert_eq!(top.len(), 2);
+        assert_eq!(top[0].pubkey, create_test_pubkey(1));
+        assert_eq!(top[0].data.p_score, 90);
+        assert_eq!(top[1].pubkey, create_test_pubkey(6));
+        assert_eq!(registry.get_top_validators(10).len(), 4);
+    }
+
+    // Assumes candidates need no dwell
+    fn add_active(registry: &mut ValidatorRegistryHostRef, pubkey: &PublicKey, era: u64) {
+        registry.update_validators(
+            vec![ValidatorUpdateData {
+                pubkey: pubkey.clone(),
+                fee: 5u64,
+                is_active: true,
+                decay_factor: 100u64,
+                delegated_stake: U512::zero(),
+                uptime: 100,
+                delegator_count: 0,
+                self_bond: U512::zero(),
+                last_fee_change_era: 0,
+            }],
+            era,
+        );
+        assert_eq!(registry.get_validator(pubkey.clone()).unwrap().state, ValidatorState::Active);
+    }
+
+    #[test]
+    fn test_lifecycle_transitions_and_dwell() {
+        let (env, mut registry, owner, keeper) = setup();
+        let pubkey = create_test_pubkey(1);
+        let update = |era: u64, is_active: bool| {
+            (
+                vec![ValidatorUpdateData {
+                    pubkey: pubkey.clone(),
+                    fee: 5u64,
+                    is_active,
+                    decay_factor: 100u64,
+                    delegated_stake: U512::zero(),
+                    uptime: 100,
+                    delegator_count: 0,
+                    self_bond: U512::zero(),
+                    last_fee_change_era: 0,
+                }],
+                era,
+            )
+        };
+
+        env.set_caller(keeper);
+        let (batch, era) = update(1, true);
+        registry.update_validators(batch, era);
+        let data = registry.get_validator(pubkey.clone()).unwrap();
+        assert_eq!((data.state, data.state_since), (ValidatorState::Candidate, 1));
+        assert!(!registry.is_valid(pubkey.clone(), 1));
+
+        // Candidates wait out their dwell before promotion
+        assert_eq!(
+            registry.try_set_validator_state(pubkey.clone(), ValidatorState::Active),
+            Err(Error::DwellNotElapsed.into())
+        );
+        env.advance_block_time(ERA_DURATION_MS);
+        let (batch, era) = update(3, true);
+        registry.update_validators(batch, era);
+        assert!(env.emitted_event(&registry, ValidatorStateChanged {
+            pubkey: pubkey.clone(),
+            from: ValidatorState::Candidate,
+            to: ValidatorState::Active,
+            era: 3,
+        }));
+        assert!(registry.is_valid(pubkey.clone(), 3));
+
+        // Going inactive is a temporary outage
+        let (batch, era) = update(4, false);
+        registry.update_validators(batch, era);
+        assert!(env.emitted_event(&registry, ValidatorStateChanged {
+            pubkey: pubkey.clone(),
+            from: ValidatorState::Active,
+            to: ValidatorState::Probation,
+            era: 4,
+        }));
+        assert!(!registry.is_valid(pubkey.clone(), 4));
+        assert_eq!(registry.get_validator_at(pubkey.clone(), 3).unwrap().state, ValidatorState::Active);
+
+        // Coming back takes the probation dwell
+        let (batch, era) = update(5, true);
+        registry.update_validators(batch, era);
+        assert_eq!(registry.get_validator(pubkey.clone()).unwrap().state, ValidatorState::Probation);
+        let (batch, era) = update(6, true);
+        registry.update_validators(batch, era);
+        assert!(registry.is_valid(pubkey.clone(), 6));
+
+        // The keeper can also put a validator on probation directly
+        registry.set_validator_state(pubkey.clone(), ValidatorState::Probation);
+        assert_eq!(registry.get_validator(pubkey.clone()).unwrap().state_since, 6);
+
+        // Jailing is for the owner, and only moves forward
+        assert_eq!(
+            registry.try_set_validator_state(pubkey.clone(), ValidatorState::Jailed),
+            Err(Error::Unauthorized.into())
+        );
+        assert_eq!(
+            registry.try_set_validator_state(pubkey.clone(), ValidatorState::Candidate),
+            Err(Error::InvalidTransition.into())
+        );
+        env.set_caller(owner);
+        registry.set_validator_state(pubkey.clone(), ValidatorState::Jailed);
+        assert_eq!(
+            registry.try_set_validator_state(pubkey.clone(), ValidatorState::Active),
+            Err(Error::InvalidTransition.into())
+        );
+
+        // Later updates keep the state the keeper cannot change
+        env.set_caller(keeper);
+        env.advance_block_time(13 * ERA_DURATION_MS);
+        let (batch, era) = update(20, true);
+        registry.update_validators(batch, era);
+        assert_eq!(registry.get_validator(pubkey.clone()).unwrap().state, ValidatorState::Jailed);
+        assert!(!registry.is_valid(pubkey.clone(), 20));
+    }
+
+    #[test]
+    fn test_retired_validators_are_pruned() {
+        let (env, mut registry, owner, keeper) = setup();
+        let retired = create_test_pubkey(1);
+        let kept = create_test_pubkey(6);
+
+        env.set_caller(owner);
+        registry.set_lifecycle_config(LifecycleConfig {
+            candidate_eras: 0,
+            ..LifecycleConfig::default()
+        });
+        env.set_caller(keeper);
+        add_active(&mut registry, &retired, 2);
+        add_active(&mut registry, &kept, 2);
+
+        assert_eq!(
+            registry.try_prune_validator(retired.clone()),
+            Err(Error::InvalidTransition.into())
+        );
+        env.set_caller(owner);
+        registry.set_validator_state(retired.clone(), ValidatorState::Retired);
+
+        let stranger = env.get_account(3);
+        env.set_caller(stranger);
+        assert_eq!(
+            registry.try_prune_validator(retired.clone()),
+            Err(Error::DwellNotElapsed.into())
+        );
+
+        env.set_caller(keeper);
+        env.advance_block_time(5 * ERA_DURATION_MS);
+        add_active(&mut registry, &create_test_pubkey(3), 8);
+        env.set_caller(stranger);
+        registry.prune_validator(retired.clone());
+        assert!(env.emitted_event(&registry, ValidatorPruned { pubkey: retired.clone(), era: 8 }));
+
+        assert!(registry.get_validator(retired.clone()).is_none());
+        assert!(!registry.is_valid(retired.clone(), 8));
+        assert_eq!(registry.get_validator_count(), 2);
+        assert!(registry.get_validators(0, 10).iter().all(|e| e.pubkey != retired));
+        assert!(registry.get_validator(kept).is_some());
+        assert_eq!(
+            registry.try_prune_validator(retired.clone()),
+            Err(Error::ValidatorNotFound.into())
+        );
+
+        // A pruned key that shows up again starts over as a new entry
+        env.set_caller(keeper);
+        registry.update_validators(
+            vec![ValidatorUpdateData {
+                pubkey: retired.clone(),
+                fee: 5u64,
+                is_active: true,
+                decay_factor: 100u64,
+                delegated_stake: U512::zero(),
+                uptime: 100,
+                delegator_count: 0,
+                self_bond: U512::zero(),
+                last_fee_change_era: 0,
+            }],
+            9,
+        );
+        assert_eq!(registry.get_validator(retired).unwrap().state_since, 9);
+        assert_eq!(registry.get_validator_count(), 3);
+    }
+
+    #[test]
+    fn test_p_avg_is_computed_on_chain() {
+        let (env, mut registry, owner, keeper) = setup();
+        let update = |byte: u8, fee: u64, is_active: bool, stake: u64| vec![ValidatorUpdateData {
+            pubkey: create_test_pubkey(byte),
+            fee,
+            is_active,
+            decay_factor: 100u64,
+            delegated_stake: U512::from(stake),
+            uptime: 100,
+            delegator_count: 0,
+            self_bond: U512::from(stake),
+            last_fee_change_era: 0,
+        }];
+
+        // Without the stake penalty the scores are plain `100 - fee`
+        env.set_caller(owner);
+        registry.set_score_formula(ScoreFormula { stake_share_weight: 0, ..ScoreFormula::standard() });
+
+        // Each batch folds into the same era's average
+        env.set_caller(keeper);
+        registry.update_validators(update(1, 10, true, 300), 1);
+        assert_eq!(registry.get_network_p_avg(), 90);
+        registry.update_validators(update(6, 40, true, 100), 1);
+        assert_eq!(registry.get_network_p_avg(), 75);
+        assert!(env.emitted_event(&registry, ValidatorsUpdated { era: 1, count: 1, p_avg: 75 }));
+
+        registry.update_validators(update(3, 0, true, 0), 1);
+        assert_eq!(registry.get_network_p_avg(), 83);
+
+        env.set_caller(owner);
+        registry.set_p_avg_mode(PAvgMode::Stake);
+        // (90 * 300 + 60 * 100 + 100 * 0) / 400
+        assert_eq!(registry.get_network_p_avg(), 82);
+        registry.set_p_avg_mode(PAvgMode::Count);
+
+        // Rewrites replace a validator's share, inactive validators drop out
+        env.set_caller(keeper);
+        registry.update_validators(update(1, 20, true, 300), 2);
+        assert_eq!(registry.get_network_p_avg(), 80);
+        registry.update_validators(update(3, 0, false, 0), 3);
+        assert_eq!(registry.get_network_p_avg(), 70);
+        assert_eq!(registry.get_p_avg_at(2), Some(80));
+
+        // Jailed validators are left out, and the result stays in range
+        env.set_caller(owner);
+        registry.set_validator_state(create_test_pubkey(1), ValidatorState::Jailed);
+        assert_eq!(registry.get_network_p_avg(), 60);
+        env.set_caller(keeper);
+        registry.update_validators(update(6, 95, true, 100), 4);
+        assert_eq!(registry.get_network_p_avg(), 10);
+
+        // With nothing left to average the last value stands
+        registry.update_validators(update(6, 95, false, 100), 5);
+        assert_eq!(registry.get_network_p_avg(), 10);
+    }
+
+    #[test]
+    fn test_stake_shares_do_not_depend_on_batch_order() {
+        let formula = ScoreFormula {
+            fee_weight: 10_000,
+            decay_weight: 10_000,
+            uptime_weight: 0,
+            stake_share_weight: 5_000,
+            stability_weight: 5_000,
+            stake_share_threshold_bps: 2_500,
+        };
+        let update = |byte: u8, stake: u64| ValidatorUpdateData {
+            pubkey: create_test_pubkey(byte),
+            fee: 5,
+            is_active: true,
+            decay_factor: 100u64,
+            delegated_stake: U512::from(stake),
+            uptime: 100,
+            delegator_count: 0,
+            self_bond: U512::from(stake),
+            last_fee_change_era: 0,
+        };
+        let inputs = |stake_share_bps: u64| ScoreInputs {
+            fee: 5,
+            is_active: true,
+            decay_factor: 100,
+            uptime: 100,
+            stake_share_bps,
+            commission_stability: 100,
+        };
+
+        for batch in [vec![update(1, 300), update(6, 100)], vec![update(6, 100), update(1, 300)]] {
+            let (env, mut registry, _owner, keeper) = setup();
+            registry.set_score_formula(formula.clone());
+            env.set_caller(keeper);
+            registry.update_validators(batch, 1);
+
+            // The first entry on a fresh registry is not scored as holding all stake
+            let score = |byte: u8| registry.get_validator(create_test_pubkey(byte)).unwrap().p_score;
+            assert_eq!(score(1), registry.preview_p_score(inputs(7_500), 2));
+            assert_eq!(score(6), registry.preview_p_score(inputs(2_500), 2));
+            assert!(score(1) < score(6));
+        }
+    }
+
+    #[test]
+    fn test_score_formula_versions() {
+        let (env, mut registry, owner, keeper) = setup();
+        let update = |byte: u8, fee: u64, stake: u64| vec![ValidatorUpdateData {
+            pubkey: create_test_pubkey(byte),
+            fee,
+            is_active: true,
+            decay_factor: 100u64,
+            delegated_stake: U512::from(stake),
+            uptime: 100,
+            delegator_count: 0,
+            self_bond: U512::from(stake),
+            last_fee_change_era: 0,
+        }];
+        let inputs = ScoreInputs {
+            fee: 5,
+            is_active: true,
+            decay_factor: 95,
+            uptime: 0,
+            stake_share_bps: 10_000,
+            commission_stability: 0,
+        };
+
+        // Version 1 is the original formula plus a penalty above 10% of the stake
+        assert_eq!(registry.get_score_formula_version(), 1);
+        assert_eq!(registry.preview_p_score(ScoreInputs { stake_share_bps: 1_000, ..inputs.clone() }, 1), 90);
+        assert_eq!(registry.preview_p_score(inputs.clone(), 1), 49);
+
+        env.set_caller(keeper);
+        registry.update_validators([update(1, 5, 300), update(6, 10, 100)].concat(), 1);
+
+        let formula = ScoreFormula {
+            fee_weight: 10_000,
+            decay_weight: 10_000,
+            uptime_weight: 0,
+            stake_share_weight: 5_000,
+            stability_weight: 5_000,
+            stake_share_threshold_bps: 2_500,
+        };
+        assert_eq!(
+            registry.try_set_score_formula(formula.clone()),
+            Err(Error::Unauthorized.into())
+        );
+        env.set_caller(owner);
+        assert_eq!(
+            registry.try_set_score_formula(ScoreFormula { uptime_weight: 10_001, ..formula.clone() }),
+            Err(Error::InvalidFormula.into())
+        );
+        assert_eq!(registry.set_score_formula(formula.clone()), 2);
+        assert!(env.emitted_event(&registry, ScoreFormulaPublished { version: 2 }));
+        assert_eq!(registry.get_score_formula(2), Some(formula));
+        assert_eq!(registry.get_score_formula(1), Some(ScoreFormula::standard()));
+
+        // A 2 point fee rise on 75% of network stake
+        env.set_caller(keeper);
+        registry.update_validators(update(1, 7, 300), 2);
+        let rescored = registry.get_validator(create_test_pubkey(1)).unwrap();
+        assert_eq!((rescored.p_score, rescored.score_version), (55, 2));
+        let untouched = registry.get_validator(create_test_pubkey(6)).unwrap();
+        assert_eq!((untouched.p_score, untouched.score_version), (63, 1));
+
+        let rescored_inputs = ScoreInputs {
+            fee: 7,
+            decay_factor: 100,
+            uptime: 100,
+            stake_share_bps: 7_500,
+            commission_stability: 80,
+            ..inputs
+        };
+        assert_eq!(registry.preview_p_score(rescored_inputs.clone(), 2), 55);
+        assert_eq!(registry.preview_p_score(rescored_inputs.clone(), 1), 52);
+        assert_eq!(
+            registry.try_preview_p_score(rescored_inputs, 3),
+            Err(Error::UnknownFormula.into())
+        );
+    }
+
+    #[test]
+    fn test_metrics_validation_and_decentralization_penalty() {
+        let (env, mut registry, owner, keeper) = setup();
+        let update = |byte: u8, uptime: u64, stake: u64, self_bond: u64, delegators: u32| ValidatorUpdateData {
+            pubkey: create_test_pubkey(byte),
+            fee: 0,
+            is_active: true,
+            decay_factor: 100u64,
+            delegated_stake: U512::from(stake),
+            uptime,
+            delegator_count: delegators,
+            self_bond: U512::from(self_bond),
+            last_fee_change_era: 0,
+        };
+
+        env.set_caller(keeper);
+        let invalid = [
+            (update(1, 101, 100, 10, 3), Error::InvalidUptime),
+            (update(1, 100, 100, 101, 3), Error::InvalidSelfBond),
+            (update(1, 100, 100, 10, 0), Error::InvalidDelegatorCount),
+            (update(1, 100, 100, 100, 3), Error::InvalidDelegatorCount),
+            (ValidatorUpdateData { last_fee_change_era: 2, ..update(1, 100, 100, 10, 3) }, Error::InvalidFeeChangeEra),
+        ];
+        for (data, error) in invalid {
+            assert_eq!(registry.try_update_validators(vec![data], 1), Err(error.into()));
+        }
+
+        env.set_caller(owner);
+        registry.set_score_formula(ScoreFormula {
+            fee_weight: 10_000,
+            decay_weight: 10_000,
+            uptime_weight: 10_000,
+            stake_share_weight: 10_000,
+            stability_weight: 0,
+            stake_share_threshold_bps: 2_000,
+        });
+
+        env.set_caller(keeper);
+        let batch = vec![
+            update(1, 100, 800, 100, 40),
+            update(6, 90, 100, 20, 5),
+            update(3, 100, 100, 100, 0),
+        ];
+        registry.update_validators(batch.clone(), 1);
+        registry.update_validators(batch, 2);
+
+        // 80% of the stake is four times the 20% threshold
+        let big = registry.get_validator(create_test_pubkey(1)).unwrap();
+        assert_eq!(big.p_score, 25);
+        assert_eq!((big.delegator_count, big.self_bond), (40, U512::from(100)));
+        // Small operators are only held back by their own uptime
+        assert_eq!(registry.get_validator(create_test_pubkey(6)).unwrap().p_score, 90);
+        assert_eq!(registry.get_validator(create_test_pubkey(3)).unwrap().p_score, 100);
+    }
+
+    #[test]
+    fn test_commission_spikes_flag_validators() {
+        let (env, mut registry, owner, keeper) = setup();
+        let pubkey = create_test_pubkey(1);
+        let update = |fee: u64| vec![ValidatorUpdateData {
+            pubkey: pubkey.clone(),
+            fee,
+            is_active: true,
+            decay_factor: 100u64,
+            delegated_stake: U512::zero(),
+            uptime: 100,
+            delegator_count: 0,
+            self_bond: U512::zero(),
+            last_fee_change_era: 0,
+        }];
+
+        let config = CommissionConfig { max_increase: 5, lookback_eras: 10, cool_off_eras: 4 };
+        env.set_caller(owner);
+        assert_eq!(
+            registry.try_set_commission_config(CommissionConfig { lookback_eras: 0, ..config.clone() }),
+            Err(Error::InvalidConfig.into())
+        );
+        registry.set_commission_config(config);
+        registry.set_lifecycle_config(LifecycleConfig { candidate_eras: 0, ..LifecycleConfig::default() });
+
+        // Small steps are fine, the rise is measured from the lowest recent fee
+        env.set_caller(keeper);
+        for (era, fee) in [(1, 5), (2, 8), (3, 3)] {
+            registry.update_validators(update(fee), era);
+        }
+        assert!(registry.is_valid(pubkey.clone(), 3));
+        assert!(!env.emitted(&registry, "CommissionSpike"));
+
+        registry.update_validators(update(9), 4);
+        assert!(env.emitted_event(&registry, CommissionSpike {
+            pubkey: pubkey.clone(),
+            era: 4,
+            from_fee: 3,
+            to_fee: 9,
+            flagged_until: 8,
+        }));
+        assert_eq!(registry.get_commission_flag(pubkey.clone()), Some(8));
+        assert_eq!(registry.get_fee_change_count(pubkey.clone()), 4);
+        assert_eq!(registry.get_fee_change(pubkey.clone(), 1), Some(FeeChange { era: 2, fee: 8 }));
+
+        // Excluded through the cool-off, whatever era the caller claims
+        env.advance_block_time(2 * ERA_DURATION_MS);
+        registry.update_validators(update(9), 7);
+        assert!(!registry.is_valid(pubkey.clone(), 7));
+        assert!(!registry.is_valid(pubkey.clone(), 10));
+        registry.update_validators(update(9), 8);
+        assert!(registry.is_valid(pubkey.clone(), 8));
+
+        // Fees older than the lookback no longer count
+        env.advance_block_time(11 * ERA_DURATION_MS);
+        registry.update_validators(update(12), 20);
+        env.advance_block_time(19 * ERA_DURATION_MS);
+        registry.update_validators(update(16), 40);
+        assert_eq!(registry.get_commission_flag(pubkey.clone()), Some(8));
+        assert!(registry.is_valid(pubkey, 40));
+    }
+
+    fn hash(bytes: &[u8]) -> Hash {
+        odra::casper_types::Digest::hash(bytes).value()
+    }
+
+    #[test]
+    fn test_committed_scores_are_proven_lazily() {
+        let (env, mut registry, owner, keeper) = setup();
+        let data: Vec<ValidatorUpdateData> = (1..=3u8)
+            .zip([1u8, 6, 3])
+            .map(|(i, byte)| ValidatorUpdateData {
+                pubkey: create_test_pubkey(byte),
+                fee: 5 * i as u64,
+                is_active: true,
+                decay_factor: 100u64,
+                delegated_stake: U512::zero(),
+                uptime: 100,
+                delegator_count: 0,
+                self_bond: U512::zero(),
+                last_fee_change_era: 0,
+            })
+            .collect();
+
+        // Three leaves: root = H(H(l0, l1), l2)
+        let leaves: Vec<Hash> = data.iter().map(|d| hash(&merkle::leaf_preimage(5, d))).collect();
+        let pair = hash(&merkle::node_preimage(&leaves[0], &leaves[1]));
+        let root = hash(&merkle::node_preimage(&pair, &leaves[2]));
+
+        // Sums over all three leaves: scores 95, 90 and 85
+        let totals = NetworkTotals { score_sum: 270, count: 3, ..Default::default() };
+
+        let stranger = env.get_account(4);
+        env.set_caller(stranger);
+        assert_eq!(
+            registry.try_prove_validator(5, data[0].clone(), vec![leaves[1], leaves[2]]),
+            Err(Error::NoCommittedRoot.into())
+        );
+        assert_eq!(registry.try_commit_scores(root, 3, totals.clone(), 5), Err(Error::Unauthorized.into()));
+
+        env.set_caller(owner);
+        registry.set_lifecycle_config(LifecycleConfig { candidate_eras: 0, ..LifecycleConfig::default() });
+        env.set_caller(keeper);
+        assert_eq!(
+            registry.try_commit_scores(root, 3, NetworkTotals { count: 4, ..totals.clone() }, 5),
+            Err(Error::InvalidTotals.into())
+        );
+        registry.commit_scores(root, 3, totals.clone(), 5);
+        assert!(env.emitted_event(&registry, ScoresCommitted { era: 5, root, count: 3 }));
+        assert_eq!(registry.try_commit_scores(root, 3, totals.clone(), 5), Err(Error::ScoresAlreadyCommitted.into()));
+        assert_eq!(registry.get_score_root(5), Some(root));
+        assert_eq!(registry.get_score_commit(5).unwrap().totals, totals);
+        assert_eq!(registry.get_last_update_era(), 5);
+
+        // The keeper's sums count for nothing until every leaf backs them
+        assert_eq!(registry.get_network_p_avg(), 80);
+
+        // Nothing is stored until someone proves it
+        env.set_caller(stranger);
+        assert!(registry.get_validator(data[0].pubkey.clone()).is_none());
+        let proven = registry.prove_validator(5, data[0].clone(), vec![leaves[1], leaves[2]]);
+        assert_eq!((proven.p_score, proven.updated_era), (95, 5));
+        assert!(env.emitted_event(&registry, ValidatorProven {
+            pubkey: data[0].pubkey.clone(),
+            era: 5,
+            p_score: 95,
+        }));
+        assert!(registry.is_valid(data[0].pubkey.clone(), 5));
+        registry.prove_validator(5, data[2].clone(), vec![pair]);
+        assert_eq!(registry.get_network_p_avg(), 80);
+        assert_eq!(registry.get_score_commit(5).unwrap().proven, 2);
+
+        let tampered = ValidatorUpdateData { fee: 0, ..data[1].clone() };
+        assert_eq!(
+            registry.try_prove_validator(5, tampered, vec![leaves[0], leaves[2]]),
+            Err(Error::InvalidProof.into())
+        );
+        assert_eq!(
+            registry.try_prove_validator(5, data[1].clone(), vec![leaves[2]]),
+            Err(Error::InvalidProof.into())
+        );
+
+        // Proven data is served from storage for the rest of the era
+        let events = env.events_count(&registry);
+        assert_eq!(registry.prove_validator(5, data[0].clone(), vec![]), proven);
+        assert_eq!(env.events_count(&registry), events);
+
+        // The last leaf verifies the sums and sets the p_avg
+        registry.prove_validator(5, data[1].clone(), vec![leaves[0], leaves[2]]);
+        assert!(env.emitted_event(&registry, ScoresVerified { era: 5, p_avg: 90 }));
+        assert_eq!(registry.get_network_p_avg(), 90);
+
+        // A newer commit does not strand proofs built against the last root
+        env.set_caller(keeper);
+        env.advance_block_time(ERA_DURATION_MS);
+        let next = [data[2].clone(), data[0].clone()];
+        let next_leaves: Vec<Hash> = next.iter().map(|d| hash(&merkle::leaf_preimage(7, d))).collect();
+        let next_root = hash(&merkle::node_preimage(&next_leaves[0], &next_leaves[1]));
+        let next_totals = NetworkTotals { score_sum: 180, count: 2, ..Default::default() };
+        registry.commit_scores(next_root, 2, next_totals, 7);
+        env.set_caller(stranger);
+        assert_eq!(registry.prove_validator(7, data[2].clone(), vec![next_leaves[1]]).updated_era, 7);
+        assert_eq!(
+            registry.try_prove_validator(5, data[2].clone(), vec![pair]),
+            Err(Error::InvalidEra.into())
+        );
+        registry.prove_validator(7, data[0].clone(), vec![next_leaves[0]]);
+        assert!(env.emitted_event(&registry, ScoresVerified { era: 7, p_avg: 90 }));
+
+        // Sums the leaves do not add up to are reported and leave the p_avg alone
+        env.set_caller(keeper);
+        env.advance_block_time(ERA_DURATION_MS);
+        let leaf = hash(&merkle::leaf_preimage(8, &data[2]));
+        let inflated = NetworkTotals { score_sum: 100, count: 1, ..Default::default() };
+        registry.commit_scores(leaf, 1, inflated.clone(), 8);
+        env.set_caller(stranger);
+        registry.prove_validator(8, data[2].clone(), vec![]);
+        assert!(env.emitted_event(&registry, ScoresMismatched { era: 8 }));
+        let commit = registry.get_score_commit(8).unwrap();
+        assert_eq!((commit.totals, commit.proven_totals.score_sum), (inflated, 85));
+        assert_eq!(registry.get_network_p_avg(), 90);
+
+        // Data left unproven through later commits goes stale, and so do old roots
+        env.set_caller(keeper);
+        env.advance_block_time(ERA_DURATION_MS);
+        registry.commit_scores([7u8; 32], 3, totals, 9);
+        assert!(!registry.is_valid(data[1].pubkey.clone(), 9));
+        assert_eq!(
+            registry.try_prove_validator(5, data[1].clone(), vec![leaves[0], leaves[2]]),
+            Err(Error::RootExpired.into())
+        );
+    }
 }
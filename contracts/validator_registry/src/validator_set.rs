@@ -0,0 +1,71 @@
+use odra::casper_types::PublicKey;
+use odra::prelude::*;
+
+/// Enumerable set of validator keys
+/// Removal swaps the last key into the freed slot, so every operation
+/// touches a constant number of slots. Order is insertion order until
+/// something is removed.
+#[odra::module]
+pub struct ValidatorSet {
+    keys: Mapping<u32, PublicKey>,
+    // One-based slot of each key, zero when absent
+    positions: Mapping<PublicKey, u32>,
+    len: Var<u32>,
+}
+
+#[odra::module]
+impl ValidatorSet {
+    pub fn len(&self) -> u32 {
+        self.len.get_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, pubkey: &PublicKey) -> bool {
+        self.positions.get_or_default(pubkey) != 0
+    }
+
+    pub fn get(&self, index: u32) -> Option<PublicKey> {
+        if index >= self.len() {
+            return None;
+        }
+        self.keys.get(&index)
+    }
+
+    /// Adds `pubkey` unless already present
+    pub fn insert(&mut self, pubkey: &PublicKey) {
+        if self.contains(pubkey) {
+            return;
+        }
+        let len = self.len();
+        self.keys.set(&len, pubkey.clone());
+        self.positions.set(pubkey, len + 1);
+        self.len.set(len + 1);
+    }
+
+    /// Returns false when `pubkey` was not in the set
+    pub fn remove(&mut self, pubkey: &PublicKey) -> bool {
+        let position = self.positions.get_or_default(pubkey);
+        if position == 0 {
+            return false;
+        }
+
+        let last = self.len() - 1;
+        if position - 1 != last {
+            if let Some(moved) = self.keys.get(&last) {
+                self.keys.set(&(position - 1), moved.clone());
+                self.positions.set(&moved, position);
+            }
+        }
+        self.positions.set(pubkey, 0);
+        self.len.set(last);
+        true
+    }
+
+    pub fn page(&self, offset: u32, limit: u32) -> Vec<PublicKey> {
+        let end = offset.saturating_add(limit).min(self.len());
+        (offset..end).filter_map(|i| self.keys.get(&i)).collect()
+    }
+}
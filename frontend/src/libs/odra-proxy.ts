@@ -7,6 +7,7 @@
 
 import {
   Args,
+  CLTypePublicKey,
   CLTypeUInt8,
   CLValue,
   PublicKey,
@@ -117,13 +118,18 @@ export async function buildOdraProxyTransaction({
  * Build inner args for stake function
  */
 export function buildStakeInnerArgs(
-  validatorPublicKey: string,
+  validatorPublicKey: string | undefined,
   currentEra: number
 ): Args {
+  // Without a validator the contract allocates by p_score
+  const validator = validatorPublicKey
+    ? CLValue.newCLOption(
+        CLValue.newCLPublicKey(PublicKey.fromHex(validatorPublicKey))
+      )
+    : CLValue.newCLOption(null, CLTypePublicKey);
+
   return Args.fromMap({
-    validator_pubkey: CLValue.newCLPublicKey(
-      PublicKey.fromHex(validatorPublicKey)
-    ),
+    validator_pubkey: validator,
     current_era: CLValue.newCLUint64(currentEra),
   });
 }
@@ -90,6 +90,18 @@ class BufferReader {
     return val;
   }
 
+  readU32(): number {
+    const val = this.buf.readUInt32LE(this.cursor);
+    this.cursor += 4;
+    return val;
+  }
+
+  readU8(): number {
+    const val = this.buf[this.cursor];
+    this.cursor += 1;
+    return val;
+  }
+
   readBool(): boolean {
     const val = this.buf[this.cursor] !== 0;
     this.cursor += 1;
@@ -170,8 +182,26 @@ export interface ValidatorData {
   decay_factor: number;
   p_score: number;
   updated_era: number;
+  state: ValidatorState;
+  state_since: number;
+  delegated_stake: string;
+  score_version: number;
+  uptime: number;
+  delegator_count: number;
+  self_bond: string;
+  last_fee_change_era: number;
 }
 
+export const VALIDATOR_STATES = [
+  "Candidate",
+  "Active",
+  "Probation",
+  "Jailed",
+  "Retired",
+] as const;
+
+export type ValidatorState = (typeof VALIDATOR_STATES)[number];
+
 export function parseValidatorData(hex: string): ValidatorData | null {
   try {
     const reader = new BufferReader(Buffer.from(hex, "hex"));
@@ -181,6 +211,14 @@ export function parseValidatorData(hex: string): ValidatorData | null {
       decay_factor: reader.readU64(),
       p_score: reader.readU64(),
       updated_era: reader.readU64(),
+      state: VALIDATOR_STATES[reader.readU8()],
+      state_since: reader.readU64(),
+      delegated_stake: reader.readU256(),
+      score_version: reader.readU32(),
+      uptime: reader.readU64(),
+      delegator_count: reader.readU32(),
+      self_bond: reader.readU256(),
+      last_fee_change_era: reader.readU64(),
     };
   } catch {
     return null;
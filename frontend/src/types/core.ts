@@ -4,6 +4,14 @@ export type ValidatorData = {
   decay_factor: number;
   p_score: number;
   updated_era: number;
+  state?: "Candidate" | "Active" | "Probation" | "Jailed" | "Retired";
+  state_since?: number;
+  delegated_stake?: string;
+  score_version?: number;
+  uptime?: number;
+  delegator_count?: number;
+  self_bond?: string;
+  last_fee_change_era?: number;
 };
 
 export type LiquidStakingStats = {
@@ -25,7 +33,7 @@ export type WithdrawalRequest = {
 };
 
 export type StakePayload = {
-  validatorPublicKey: string;
+  validatorPublicKey?: string;
   amount: string;
   currentEra: number;
   waitForConfirmation?: boolean;
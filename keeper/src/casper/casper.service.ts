@@ -18,6 +18,8 @@ export interface ValidatorInfo {
   fee: number;
   isActive: boolean;
   totalStake: bigint;
+  selfBond: bigint;
+  delegatorCount: number;
   lastEraReward?: bigint; // Reward in most recent era
 }
 
@@ -86,25 +88,57 @@ export class CasperService {
     try {
       const auctionInfo = await this.client.getLatestAuctionInfo();
       const validators: ValidatorInfo[] = [];
+      const delegations = new Map<string, { stake: bigint; count: number }>();
+      const addDelegation = (validator: string, amount: bigint) => {
+        // The registry expects delegators and delegated stake to go together
+        if (amount === 0n) return;
+        const entry = delegations.get(validator) || { stake: 0n, count: 0 };
+        entry.stake += amount;
+        entry.count += 1;
+        delegations.set(validator, entry);
+      };
 
       for (const bid of auctionInfo.auctionState.bids) {
         const publicKey = bid.publicKey.toHex();
         const bidData = bid.bid.unified || bid.bid.validator;
 
+        // Casper 2.x lists delegations as bids of their own
+        const delegator = bid.bid.delegator;
+        if (delegator) {
+          addDelegation(
+            delegator.validatorPublicKey.toHex(),
+            BigInt(delegator.stakedAmount.toString()),
+          );
+        }
+
         if (bidData) {
           const delegationRate = bidData.delegationRate;
           const inactive = bidData.inactive || false;
           const stakedAmount = BigInt(bidData.stakedAmount.toString());
 
+          for (const d of bid.bid.unified?.delegators || []) {
+            addDelegation(publicKey, BigInt(d.stakedAmount.toString()));
+          }
+
           validators.push({
             publicKey,
             fee: delegationRate,
             isActive: !inactive,
             totalStake: stakedAmount,
+            selfBond: stakedAmount,
+            delegatorCount: 0,
           });
         }
       }
 
+      for (const validator of validators) {
+        const delegated = delegations.get(validator.publicKey);
+        if (delegated) {
+          validator.totalStake += delegated.stake;
+          validator.delegatorCount = delegated.count;
+        }
+      }
+
       return validators;
     } catch (error) {
       this.logger.error(
@@ -326,4 +360,51 @@ export class CasperService {
       throw error;
     }
   }
+
+  /**
+   * Move delegated CSPR to another validator via auction system contract
+   * Skips unbonding, the stake starts earning with the new validator
+   */
+  async redelegate(
+    validatorPublicKey: string,
+    newValidatorPublicKey: string,
+    amount: string,
+  ): Promise<string> {
+    try {
+      const auctionHash =
+        this.configService.get<string>('auctionContractHash') || '';
+
+      if (!auctionHash) {
+        throw new Error('Auction contract hash not configured');
+      }
+
+      const args = Args.fromMap({
+        delegator: CLValue.newCLPublicKey(this.publicKey),
+        validator: CLValue.newCLPublicKey(
+          PublicKey.fromHex(validatorPublicKey),
+        ),
+        amount: CLValue.newCLUInt512(amount),
+        new_validator: CLValue.newCLPublicKey(
+          PublicKey.fromHex(newValidatorPublicKey),
+        ),
+      });
+
+      this.logger.log(
+        `Redelegating ${amount} motes from ${validatorPublicKey} to ${newValidatorPublicKey}`,
+      );
+
+      const deployHash = await this.sendDeploy(
+        `hash-${auctionHash}`,
+        'redelegate',
+        args,
+        '2500000000', // 2.5 CSPR fixed cost
+      );
+
+      this.logger.log(`Redelegation transaction sent: ${deployHash}`);
+      return deployHash;
+    } catch (error) {
+      this.logger.error(`Failed to redelegate: ${getErrorMessage(error)}`);
+      throw error;
+    }
+  }
 }
@@ -121,19 +121,19 @@ export class DelegationService {
 
   /**
    * Get pending delegations from the liquid staking contract
-   * Note: This queries the contract's get_pending_delegations entry point
+   * Note: This queries the contract's get_ready_delegations entry point
    */
   private async getPendingDelegations(): Promise<PendingDelegation[]> {
     const contractHash =
       this.configService.get<string>('liquidStakingContractPackageHash') || '';
 
     try {
-      // Call the contract's get_pending_delegations entry point
+      // Only batches above the validator's minimum delegation are actionable
       const args = Args.fromMap({});
 
       const deployHash = await this.casperService.sendDeploy(
         contractHash,
-        'get_pending_delegations',
+        'get_ready_delegations',
         args,
         '1000000000', // 1 CSPR gas for read operation
       );
@@ -362,9 +362,11 @@ export class DelegationService {
     this.logger.log(`Undelegation successful: ${undelegateHash}`);
 
     // Step 2: Confirm undelegation in contract
+    const currentEra = await this.casperService.getCurrentEra();
     const confirmHash = await this.confirmUndelegation(
       undelegation.validator,
       undelegation.amount,
+      currentEra,
     );
 
     const confirmSuccess = await this.casperService.waitForDeploy(confirmHash);
@@ -421,6 +423,7 @@ export class DelegationService {
   private async confirmUndelegation(
     validatorPubKey: string,
     amount: bigint,
+    currentEra: number,
   ): Promise<string> {
     const contractHash =
       this.configService.get<string>('liquidStakingContractPackageHash') || '';
@@ -428,6 +431,7 @@ export class DelegationService {
     const args = Args.fromMap({
       validator: CLValue.newCLPublicKey(PublicKey.fromHex(validatorPubKey)),
       amount: CLValue.newCLUInt512(amount.toString()),
+      current_era: CLValue.newCLUint64(currentEra),
     });
 
     return this.casperService.sendDeploy(
@@ -443,7 +447,10 @@ export class DelegationService {
    * Called after unbonding period completes
    * Note: This sends CSPR with the transaction via the payment amount
    */
-  async depositFromUndelegation(amount: bigint): Promise<string> {
+  async depositFromUndelegation(
+    validatorPubKey: string,
+    amount: bigint,
+  ): Promise<string> {
     const contractHash =
       this.configService.get<string>('liquidStakingContractPackageHash') || '';
     const chainName =
@@ -456,7 +463,11 @@ export class DelegationService {
       .from(this.publicKey)
       .byPackageHash(contractHash)
       .entryPoint('deposit_from_undelegation')
-      .runtimeArgs(Args.fromMap({}))
+      .runtimeArgs(
+        Args.fromMap({
+          validator: CLValue.newCLPublicKey(PublicKey.fromHex(validatorPubKey)),
+        }),
+      )
       .chainName(chainName)
       .payment(Number(amount) + 5_000_000_000) // Amount + gas
       .build();
@@ -27,6 +27,13 @@ interface PendingUndelegation {
   era: number;
 }
 
+interface PendingRedelegation {
+  id: number;
+  from: string; // hex string
+  to: string; // hex string
+  amount: string; // U512 as string
+}
+
 @Injectable()
 export class LiquidStakingService {
   private readonly logger = new Logger(LiquidStakingService.name);
@@ -93,6 +100,42 @@ export class LiquidStakingService {
     this.logger.log(`Harvest completed: ${deployHash}`);
   }
 
+  /**
+   * Refresh the validator shortlist that stake routing picks from.
+   * Runs after each registry update so new scores reach unpinned stakes.
+   */
+  async refreshStakeCandidates(): Promise<void> {
+    try {
+      const contractHash =
+        this.configService.get<string>('liquidStakingContractPackageHash') ||
+        '';
+      const currentEra = await this.casperService.getCurrentEra();
+
+      const args = Args.fromMap({
+        current_era: CLValue.newCLUint64(currentEra),
+      });
+
+      const deployHash = await this.casperService.sendDeploy(
+        contractHash,
+        'refresh_stake_candidates',
+        args,
+        '15000000000',
+      );
+
+      const success = await this.casperService.waitForDeploy(deployHash);
+      if (!success) {
+        throw new Error(`Stake candidate refresh failed: ${deployHash}`);
+      }
+
+      this.logger.log(`Stake candidates refreshed: ${deployHash}`);
+    } catch (error) {
+      this.logger.error(
+        `Stake candidate refresh failed: ${getErrorMessage(error)}`,
+        getErrorStack(error),
+      );
+    }
+  }
+
   /**
    * Process pending delegations:
    * 1. Query get_pending_delegations from contract via CSPR Cloud API
@@ -404,9 +447,11 @@ export class LiquidStakingService {
     this.logger.log(`Undelegated from validator: ${undelegateHash}`);
 
     // Step 2: Confirm undelegation to contract
+    const currentEra = await this.casperService.getCurrentEra();
     const confirmArgs = Args.fromMap({
       validator: CLValue.newCLPublicKey(PublicKey.fromHex(pending.validator)),
       amount: CLValue.newCLUInt512(pending.amount),
+      current_era: CLValue.newCLUint64(currentEra),
     });
 
     const confirmHash = await this.casperService.sendDeploy(
@@ -426,7 +471,6 @@ export class LiquidStakingService {
     );
 
     // Track unbonding for automatic deposit after 7 eras
-    const currentEra = await this.casperService.getCurrentEra();
     this.unbondingTracker.addUnbonding(
       pending.validator,
       pending.amount,
@@ -522,4 +566,46 @@ export class LiquidStakingService {
 
     this.logger.log(`Deposited unbonding CSPR to contract: ${depositHash}`);
   }
+
+  /**
+   * Execute a redelegation recorded by start_redelegation
+   * 1. Redelegate on the auction contract
+   * 2. Confirm so the contract moves the validator stake
+   * A failed native redelegation is cancelled to free the record
+   */
+  async processRedelegation(redelegation: PendingRedelegation): Promise<void> {
+    const contractHash =
+      this.configService.get<string>('liquidStakingContractPackageHash') || '';
+
+    const redelegateHash = await this.casperService.redelegate(
+      redelegation.from,
+      redelegation.to,
+      redelegation.amount,
+    );
+    const redelegateSuccess =
+      await this.casperService.waitForDeploy(redelegateHash);
+
+    const entryPoint = redelegateSuccess
+      ? 'confirm_redelegation'
+      : 'cancel_redelegation';
+    const args = Args.fromMap({
+      id: CLValue.newCLUint64(redelegation.id),
+    });
+
+    const hash = await this.casperService.sendDeploy(
+      contractHash,
+      entryPoint,
+      args,
+      '5000000000', // 5 CSPR
+    );
+
+    const success = await this.casperService.waitForDeploy(hash);
+    if (!success) {
+      throw new Error(`${entryPoint} failed: ${hash}`);
+    }
+
+    this.logger.log(
+      `Redelegation ${redelegation.id} ${redelegateSuccess ? 'confirmed' : 'cancelled'}: ${hash}`,
+    );
+  }
 }
@@ -73,6 +73,7 @@ export class SchedulerService implements OnModuleInit {
     this.logger.log('Executing scheduled validator update...');
     try {
       await this.validatorRegistryService.updateValidators();
+      await this.liquidStakingService.refreshStakeCandidates();
     } catch (error) {
       this.logger.error(
         `Scheduled validator update failed: ${error instanceof Error ? error.message : String(error)}`,
@@ -8,6 +8,11 @@ interface ValidatorUpdateData {
   fee: number;
   isActive: boolean;
   decayFactor: number;
+  delegatedStake: bigint;
+  uptime: number;
+  delegatorCount: number;
+  selfBond: bigint;
+  lastFeeChangeEra: number;
 }
 
 function getErrorMessage(error: unknown): string {
@@ -26,6 +31,8 @@ export class ValidatorRegistryService {
   private readonly DECAY_WINDOW = 30;
 
   private lastProcessedEra = 0;
+  // Last fee seen per validator and the era it was first seen at
+  private readonly feeChanges = new Map<string, { fee: number; era: number }>();
 
   constructor(
     private casperService: CasperService,
@@ -69,6 +76,11 @@ export class ValidatorRegistryService {
             v,
             performanceScores.get(v.publicKey),
           ),
+          delegatedStake: v.totalStake,
+          uptime: this.calculateUptime(performanceScores.get(v.publicKey)),
+          delegatorCount: v.delegatorCount,
+          selfBond: v.selfBond,
+          lastFeeChangeEra: this.trackFeeChange(v, currentEra),
         }))
         .sort((a, b) => b.decayFactor - a.decayFactor);
 
@@ -200,6 +212,24 @@ export class ValidatorRegistryService {
     return Math.max(80, Math.min(100, decayFactor));
   }
 
+  private calculateUptime(performanceScore?: number): number {
+    if (performanceScore === undefined) {
+      return 100;
+    }
+    return Math.max(0, Math.min(100, Math.round(performanceScore)));
+  }
+
+  private trackFeeChange(validator: ValidatorInfo, currentEra: number): number {
+    const seen = this.feeChanges.get(validator.publicKey);
+    if (seen && seen.fee === validator.fee) {
+      return seen.era;
+    }
+    // A fee seen for the first time has no known change yet
+    const era = seen ? currentEra : 0;
+    this.feeChanges.set(validator.publicKey, { fee: validator.fee, era });
+    return era;
+  }
+
   private async sendUpdateToContract(
     validators: ValidatorUpdateData[],
     currentEra: number,
@@ -219,22 +249,27 @@ export class ValidatorRegistryService {
       const feeBytes = CLValue.newCLUint64(v.fee).bytes();
       const isActiveBytes = CLValue.newCLValueBool(v.isActive).bytes();
       const decayFactorBytes = CLValue.newCLUint64(v.decayFactor).bytes();
+      const parts = [
+        pubkeyBytes,
+        feeBytes,
+        isActiveBytes,
+        decayFactorBytes,
+        CLValue.newCLUInt512(v.delegatedStake.toString()).bytes(),
+        CLValue.newCLUint64(v.uptime).bytes(),
+        CLValue.newCLUInt32(v.delegatorCount).bytes(),
+        CLValue.newCLUInt512(v.selfBond.toString()).bytes(),
+        CLValue.newCLUint64(v.lastFeeChangeEra).bytes(),
+      ];
 
       const structBytes = new Uint8Array(
-        pubkeyBytes.length +
-          feeBytes.length +
-          isActiveBytes.length +
-          decayFactorBytes.length,
+        parts.reduce((sum, part) => sum + part.length, 0),
       );
 
       let offset = 0;
-      structBytes.set(pubkeyBytes, offset);
-      offset += pubkeyBytes.length;
-      structBytes.set(feeBytes, offset);
-      offset += feeBytes.length;
-      structBytes.set(isActiveBytes, offset);
-      offset += isActiveBytes.length;
-      structBytes.set(decayFactorBytes, offset);
+      for (const part of parts) {
+        structBytes.set(part, offset);
+        offset += part.length;
+      }
 
       return structBytes;
     });
@@ -255,7 +290,6 @@ export class ValidatorRegistryService {
 
     const args = Args.fromMap({
       validators_data: CLValue.newCLAny(vecBytes),
-      p_avg: CLValue.newCLUint64(80),
       current_era: CLValue.newCLUint64(currentEra),
     });
 